@@ -112,6 +112,14 @@ impl AABB {
 		true
 	}
 
+	pub fn pad(&self, delta: f64) -> AABB {
+		AABB {
+			x: self.x.expand(2.0 * delta),
+			y: self.y.expand(2.0 * delta),
+			z: self.z.expand(2.0 * delta)
+		}
+	}
+
 	pub fn longest_axis(&self) -> usize {
 		if self.x.size() > self.y.size() {
 			if self.x.size() > self.z.size() { 0 } else { 2 }
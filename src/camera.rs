@@ -1,5 +1,5 @@
 use crate::color::{luminance, write_color};
-use crate::ray::Ray;
+use crate::ray::{Ray, RayDifferentials};
 use indicatif::{ProgressBar, ProgressIterator};
 use std::f64::consts::PI;
 use std::fs::File;
@@ -31,6 +31,9 @@ pub struct Camera {
 	defocus_angle: f64,
 	defocus_disk_u: Vec3,
 	defocus_disk_v: Vec3,
+
+	shutter_open: f64,
+	shutter_close: f64,
 }
 
 impl Camera {
@@ -93,10 +96,20 @@ impl Camera {
 
 			defocus_angle,
 			defocus_disk_u,
-			defocus_disk_v
+			defocus_disk_v,
+
+			shutter_open: 0.0,
+			shutter_close: 1.0
 		}
 	}
 
+	// Rays get times spread evenly between the two, which keyframes and moving spheres are timed against
+	pub fn with_shutter(mut self, open: f64, close: f64) -> Self {
+		self.shutter_open = open;
+		self.shutter_close = close;
+		self
+	}
+
 	pub fn render(&self, world: Box<dyn Hittable>, image_file: &mut File) -> std::io::Result<()> {
 		writeln!(image_file, "P3")?;
 		writeln!(image_file, "{} {}", self.image_width, self.image_height)?;
@@ -174,11 +187,14 @@ impl Camera {
 	fn ray_color(&self, ray: Ray, depth: u32, world: &Box<dyn Hittable>) -> Vec3 {
 		if depth <= 0 { return Vec3::ZERO }
 
-		if let Some(hit_record) = world.hit(ray, Interval::new(0.001, f64::MAX)) {
+		if let Some(mut hit_record) = world.hit(ray, Interval::new(0.001, f64::MAX)) {
+			hit_record.compute_differentials(&ray);
+
 			let emission_color = hit_record.material.emitted(
 				hit_record.u,
 				hit_record.v,
-				hit_record.position
+				hit_record.position,
+				hit_record.normal
 			);
 
 			let material = hit_record.material.as_ref();
@@ -215,9 +231,17 @@ impl Camera {
 		};
 
 		let ray_direction = pixel_sample - ray_origin;
-		let ray_time = fastrand::f64();
+		let ray_time = self.shutter_open + fastrand::f64() * (self.shutter_close - self.shutter_open);
+
+		// neighbouring pixels share the origin, so defocus blur does not widen the footprint
+		let differentials = RayDifferentials {
+			rx_origin: ray_origin,
+			rx_direction: ray_direction + self.pixel_delta_u,
+			ry_origin: ray_origin,
+			ry_direction: ray_direction + self.pixel_delta_v
+		};
 
-		Ray::new(ray_origin, ray_direction, ray_time)
+		Ray::new(ray_origin, ray_direction, ray_time).with_differentials(differentials)
 	}
 
 	fn defocus_disk_sample(&self) -> Vec3 {
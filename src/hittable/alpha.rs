@@ -0,0 +1,116 @@
+use std::sync::Arc;
+use crate::texture::texture::Texture;
+use crate::util::vec3::Vec3;
+
+pub enum AlphaMode {
+	// opacity is the probability of a ray hitting the surface
+	STOCHASTIC,
+	// surface is solid where opacity is at or above the cutoff
+	THRESHOLD(f64)
+}
+
+// Opacity texture for cutouts (leaves, fences, ...).
+// Consulted during intersection, so every ray including shadow rays passes through transparent texels.
+pub struct AlphaMask {
+	texture: Arc<dyn Texture>,
+	mode: AlphaMode
+}
+
+impl AlphaMask {
+	pub fn new(texture: Arc<dyn Texture>, mode: AlphaMode) -> Self {
+		AlphaMask { texture, mode }
+	}
+
+	pub fn stochastic(texture: Arc<dyn Texture>) -> Self {
+		AlphaMask { texture, mode: AlphaMode::STOCHASTIC }
+	}
+
+	pub fn threshold(texture: Arc<dyn Texture>, cutoff: f64) -> Self {
+		AlphaMask { texture, mode: AlphaMode::THRESHOLD(cutoff) }
+	}
+
+	pub fn opacity(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> f64 {
+		let value = self.texture.value_at(u, v, p, normal);
+		((value.x + value.y + value.z) / 3.0).clamp(0.0, 1.0)
+	}
+
+	pub fn is_transparent(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> bool {
+		let opacity = self.opacity(u, v, p, normal);
+
+		match self.mode {
+			AlphaMode::STOCHASTIC => {
+				if opacity >= 1.0 { return false; }
+				if opacity <= 0.0 { return true; }
+				fastrand::f64() >= opacity
+			}
+			AlphaMode::THRESHOLD(cutoff) => opacity < cutoff
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hittable::hittable::{Hittable, HittableList};
+	use crate::hittable::quad::Quad;
+	use crate::hittable::sphere::Sphere;
+	use crate::hittable::triangle::Triangle;
+	use crate::material::{Lambertian, Material};
+	use crate::ray::Ray;
+	use crate::texture::debug::UVCheckerTexture;
+	use crate::texture::texture::SolidColorTexture;
+	use crate::util::interval::Interval;
+
+	fn gray() -> Arc<dyn Material> {
+		Arc::new(Lambertian::from_color(Vec3::new(0.5, 0.5, 0.5)))
+	}
+
+	fn opacity(value: f64) -> Arc<dyn Texture> {
+		Arc::new(SolidColorTexture::from_rgb(value, value, value))
+	}
+
+	// z of the closest hit straight down from above (x, y)
+	fn hit_from_above(object: &dyn Hittable, x: f64, y: f64) -> Option<f64> {
+		let ray = Ray::new(Vec3::new(x, y, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+		object.hit(ray, Interval::new(0.001, f64::INFINITY)).map(|hit| hit.position.z)
+	}
+
+	#[test]
+	fn threshold_keeps_opacity_at_the_cutoff() {
+		let mask = AlphaMask::threshold(opacity(0.5), 0.5);
+		assert!(!mask.is_transparent(0.0, 0.0, Vec3::ZERO, Vec3::ZERO));
+
+		let mask = AlphaMask::threshold(opacity(0.49), 0.5);
+		assert!(mask.is_transparent(0.0, 0.0, Vec3::ZERO, Vec3::ZERO));
+	}
+
+	#[test]
+	fn rays_pass_through_cutouts() {
+		// the left half of the quad is below the cutoff, the right half above it
+		let checker = Arc::new(UVCheckerTexture::new(2.0, 1.0, opacity(0.25), opacity(0.75)));
+		let cutout = Quad::new(Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), gray())
+			.with_alpha_mask(AlphaMask::threshold(checker, 0.5));
+
+		let mut scene = HittableList::new();
+		scene.add(Box::new(cutout));
+		scene.add(Box::new(Quad::new(Vec3::new(0.0, 0.0, -1.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), gray())));
+
+		assert_eq!(hit_from_above(&scene, 0.25, 0.5), Some(-1.0));
+		assert_eq!(hit_from_above(&scene, 0.75, 0.5), Some(0.0));
+	}
+
+	#[test]
+	fn every_primitive_consults_its_mask() {
+		for (value, solid) in [(0.0, false), (1.0, true)] {
+			let mask = || AlphaMask::threshold(opacity(value), 0.5);
+
+			let quad = Quad::new(Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), gray()).with_alpha_mask(mask());
+			let triangle = Triangle::new(Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), gray()).with_alpha_mask(mask());
+			let sphere = Sphere::new_stationary(Vec3::ZERO, 0.5, gray()).with_alpha_mask(mask());
+
+			assert_eq!(hit_from_above(&quad, 0.25, 0.25).is_some(), solid);
+			assert_eq!(hit_from_above(&triangle, 0.25, 0.25).is_some(), solid);
+			assert_eq!(hit_from_above(&sphere, 0.1, 0.1).is_some(), solid);
+		}
+	}
+}
@@ -0,0 +1,116 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+use crate::AABB::AABB;
+use crate::hittable::disk::Disk;
+use crate::hittable::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::util::interval::Interval;
+use crate::util::vec3::Vec3;
+
+// Upright cone with its base circle on `base` and the apex `height` above it, other orientations
+// go through a TransformInstance. u goes around the axis like on a sphere, v goes up to the apex.
+pub struct Cone {
+	base: Vec3,
+	radius: f64,
+	height: f64,
+	material: Arc<dyn Material>,
+	cap: Option<Disk>,
+	bbox: AABB
+}
+
+impl Cone {
+	pub fn new(base: Vec3, radius: f64, height: f64, material: Arc<dyn Material>) -> Self {
+		let cap = Disk::new(base, Vec3::new(0.0, -1.0, 0.0), radius, material.clone());
+		let bbox = AABB::from_corners(
+			base - Vec3::new(radius, 0.0, radius),
+			base + Vec3::new(radius, height, radius)
+		);
+
+		Cone { base, radius, height, material, cap: Some(cap), bbox }
+	}
+
+	// Leaves the bottom open
+	pub fn without_cap(mut self) -> Self {
+		self.cap = None;
+		self
+	}
+
+	fn side_hit(&self, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
+		let origin = ray.origin - self.base;
+		let direction = ray.direction;
+
+		// x^2 + z^2 = k^2 (height - y)^2 with k the radius shrinking per unit of height
+		let k_sq = (self.radius / self.height) * (self.radius / self.height);
+		let to_apex = self.height - origin.y;
+		let a = direction.x * direction.x + direction.z * direction.z - k_sq * direction.y * direction.y;
+		let h = origin.x * direction.x + origin.z * direction.z + k_sq * to_apex * direction.y;
+		let c = origin.x * origin.x + origin.z * origin.z - k_sq * to_apex * to_apex;
+
+		// rays parallel to the slope cross the double cone only once
+		let roots = if a.abs() < 1e-12 {
+			if h.abs() < 1e-12 { return None; }
+			[-c / (2.0 * h), f64::INFINITY]
+		} else {
+			let discriminant = h * h - a * c;
+			if discriminant < 0.0 {
+				return None
+			}
+			let sqrt_d = f64::sqrt(discriminant);
+			let (t0, t1) = ((-h - sqrt_d) / a, (-h + sqrt_d) / a);
+			[t0.min(t1), t0.max(t1)]
+		};
+
+		for t in roots {
+			if !ray_t.surrounds(t) { continue; }
+
+			// also rejects the mirrored cone above the apex
+			let p = origin + t * direction;
+			if p.y < 0.0 || p.y > self.height { continue; }
+
+			let rho = f64::sqrt(p.x * p.x + p.z * p.z);
+			let u = ((-p.z).atan2(p.x) + PI) / (2.0 * PI);
+			let v = p.y / self.height;
+
+			// the apex has no well defined normal or direction around it
+			let (outward_normal, dpdu, dpdv) = if rho < 1e-8 {
+				(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(-1.0, 0.0, 0.0))
+			} else {
+				let radial = Vec3::new(p.x, 0.0, p.z) / rho;
+				(
+					(self.height * radial + Vec3::new(0.0, self.radius, 0.0)).unit(),
+					2.0 * PI * Vec3::new(p.z, 0.0, -p.x),
+					Vec3::new(0.0, self.height, 0.0) - self.radius * radial
+				)
+			};
+
+			return Some(HitRecord::new(
+				ray,
+				t,
+				ray.at(t),
+				outward_normal,
+				self.material.clone(),
+				u,
+				v
+			).with_tangents(dpdu, dpdv));
+		}
+
+		None
+	}
+}
+
+impl Hittable for Cone {
+	fn hit(&self, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
+		let side = self.side_hit(ray, ray_t);
+
+		let max = side.as_ref().map_or(ray_t.max, |hit_record| hit_record.t);
+		match self.cap.as_ref().and_then(|cap| cap.hit(ray, Interval::new(ray_t.min, max))) {
+			Some(hit_record) => Some(hit_record),
+			None => side
+		}
+	}
+
+	fn bounding_box(&self) -> AABB {
+		self.bbox
+	}
+}
@@ -0,0 +1,106 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+use crate::AABB::AABB;
+use crate::hittable::disk::Disk;
+use crate::hittable::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::util::interval::Interval;
+use crate::util::vec3::Vec3;
+
+// Upright cylinder standing on `base`, other orientations go through a TransformInstance.
+// u goes around the axis like on a sphere, v goes up the side.
+pub struct Cylinder {
+	base: Vec3,
+	radius: f64,
+	height: f64,
+	material: Arc<dyn Material>,
+	// bottom and top
+	caps: Option<[Disk; 2]>,
+	bbox: AABB
+}
+
+impl Cylinder {
+	pub fn new(base: Vec3, radius: f64, height: f64, material: Arc<dyn Material>) -> Self {
+		let caps = [
+			Disk::new(base, Vec3::new(0.0, -1.0, 0.0), radius, material.clone()),
+			Disk::new(base + Vec3::new(0.0, height, 0.0), Vec3::new(0.0, 1.0, 0.0), radius, material.clone())
+		];
+		let bbox = AABB::from_corners(
+			base - Vec3::new(radius, 0.0, radius),
+			base + Vec3::new(radius, height, radius)
+		);
+
+		Cylinder { base, radius, height, material, caps: Some(caps), bbox }
+	}
+
+	// Open tube
+	pub fn without_caps(mut self) -> Self {
+		self.caps = None;
+		self
+	}
+
+	fn side_hit(&self, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
+		let origin = ray.origin - self.base;
+		let direction = ray.direction;
+
+		// x^2 + z^2 = r^2, rays along the axis never touch the side
+		let a = direction.x * direction.x + direction.z * direction.z;
+		if a < 1e-12 { return None; }
+		let h = origin.x * direction.x + origin.z * direction.z;
+		let c = origin.x * origin.x + origin.z * origin.z - self.radius * self.radius;
+		let discriminant = h * h - a * c;
+
+		if discriminant < 0.0 {
+			return None
+		}
+
+		let sqrt_d = f64::sqrt(discriminant);
+
+		// the far side shows when the near one is cut off or the tube is open
+		for t in [(-h - sqrt_d) / a, (-h + sqrt_d) / a] {
+			if !ray_t.surrounds(t) { continue; }
+
+			let p = origin + t * direction;
+			if p.y < 0.0 || p.y > self.height { continue; }
+
+			let outward_normal = Vec3::new(p.x, 0.0, p.z) / self.radius;
+			let u = ((-p.z).atan2(p.x) + PI) / (2.0 * PI);
+			let v = p.y / self.height;
+
+			let dpdu = 2.0 * PI * Vec3::new(p.z, 0.0, -p.x);
+			let dpdv = Vec3::new(0.0, self.height, 0.0);
+
+			return Some(HitRecord::new(
+				ray,
+				t,
+				ray.at(t),
+				outward_normal,
+				self.material.clone(),
+				u,
+				v
+			).with_tangents(dpdu, dpdv));
+		}
+
+		None
+	}
+}
+
+impl Hittable for Cylinder {
+	fn hit(&self, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
+		let mut closest = self.side_hit(ray, ray_t);
+
+		for cap in self.caps.iter().flatten() {
+			let max = closest.as_ref().map_or(ray_t.max, |hit_record| hit_record.t);
+			if let Some(hit_record) = cap.hit(ray, Interval::new(ray_t.min, max)) {
+				closest = Some(hit_record);
+			}
+		}
+
+		closest
+	}
+
+	fn bounding_box(&self) -> AABB {
+		self.bbox
+	}
+}
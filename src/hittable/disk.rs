@@ -0,0 +1,113 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+use crate::AABB::AABB;
+use crate::hittable::hittable::{HitRecord, Hittable};
+use crate::hittable::plane::Plane;
+use crate::material::Material;
+use crate::ONB::OrthonormalBasis;
+use crate::ray::Ray;
+use crate::util::interval::Interval;
+use crate::util::vec3::Vec3;
+
+// Flat ring facing `normal`. u goes counter-clockwise around the normal, v goes from the outer edge inwards.
+pub struct Annulus {
+	plane: Plane,
+
+	center: Vec3,
+	// in-plane axes, u = 0 lies along the first
+	axes: (Vec3, Vec3),
+	inner_radius: f64,
+	outer_radius: f64,
+
+	material: Arc<dyn Material>,
+	bbox: AABB
+}
+
+impl Annulus {
+	pub fn new(center: Vec3, normal: Vec3, inner_radius: f64, outer_radius: f64, material: Arc<dyn Material>) -> Self {
+		let normal = normal.unit();
+		let first_axis = OrthonormalBasis::new(normal).u;
+		let axes = (first_axis, normal.cross(first_axis));
+
+		// a tilted circle reaches sin(angle between normal and axis) * radius along each axis
+		let extent = outer_radius * Vec3::new(
+			(1.0 - normal.x * normal.x).max(0.0).sqrt(),
+			(1.0 - normal.y * normal.y).max(0.0).sqrt(),
+			(1.0 - normal.z * normal.z).max(0.0).sqrt()
+		);
+		let bbox = AABB::from_corners(center - extent, center + extent);
+
+		Annulus {
+			plane: Plane::new(normal, center),
+			center,
+			axes,
+			inner_radius,
+			outer_radius,
+			material,
+			bbox
+		}
+	}
+}
+
+impl Hittable for Annulus {
+	fn hit(&self, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
+		let (t, intersection) = self.plane.hit(ray, ray_t)?;
+
+		let offset = intersection - self.center;
+		let distance = offset.length();
+		if distance > self.outer_radius || distance < self.inner_radius {
+			return None;
+		}
+
+		let (x, y) = (offset.dot(self.axes.0), offset.dot(self.axes.1));
+		let mut angle = y.atan2(x);
+		if angle < 0.0 { angle += 2.0 * PI; }
+
+		let width = self.outer_radius - self.inner_radius;
+		let u = angle / (2.0 * PI);
+		let v = (self.outer_radius - distance) / width;
+
+		// the radial direction is undefined at the very center
+		let (dpdu, dpdv) = if distance < 1e-8 {
+			self.axes
+		} else {
+			let radial = offset / distance;
+			(2.0 * PI * self.plane.normal.cross(offset), -width * radial)
+		};
+
+		Some(HitRecord::new(
+			ray,
+			t,
+			intersection,
+			self.plane.normal,
+			self.material.clone(),
+			u,
+			v
+		).with_tangents(dpdu, dpdv))
+	}
+
+	fn bounding_box(&self) -> AABB {
+		self.bbox
+	}
+}
+
+// Annulus without a hole
+pub struct Disk {
+	annulus: Annulus
+}
+
+impl Disk {
+	pub fn new(center: Vec3, normal: Vec3, radius: f64, material: Arc<dyn Material>) -> Self {
+		Disk { annulus: Annulus::new(center, normal, 0.0, radius, material) }
+	}
+}
+
+impl Hittable for Disk {
+	fn hit(&self, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
+		self.annulus.hit(ray, ray_t)
+	}
+
+	fn bounding_box(&self) -> AABB {
+		self.annulus.bounding_box()
+	}
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::hittable::mesh::{MeshBuffers, MeshSettings, TriangleMesh};
+use crate::material::Material;
+use crate::texture::texture::Texture;
+use crate::util::vec3::Vec3;
+
+// True displacement: meshes are tessellated and their vertices moved along the normal
+// by a height texture before the BVH is built, so silhouettes change unlike bump mapping.
+pub struct Displacement {
+	pub texture: Arc<dyn Texture>,
+	pub scale: f64,
+	// every level splits each triangle edge in half
+	pub subdivision_level: u32,
+	// extra room added to each generated triangle's bounding box
+	pub bounds_padding: f64
+}
+
+impl Displacement {
+	pub fn quad(&self, q: Vec3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> TriangleMesh {
+		let positions = [q, q + u, q + u + v, q + v];
+		let uvs = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+		let indices = [[0, 1, 2], [0, 2, 3]];
+
+		self.mesh(&positions, Some(&uvs), &indices, material)
+	}
+
+	// Indexed triangle mesh. Normals are averaged per vertex and tessellated vertices are welded
+	// by the source vertices they lie between, so faces sharing an edge displace it once and
+	// no cracks open up. Edges along uv seams use different source vertices and may still split.
+	pub fn mesh(
+		&self,
+		positions: &[Vec3],
+		uvs: Option<&[(f64, f64)]>,
+		indices: &[[usize; 3]],
+		material: Arc<dyn Material>
+	) -> TriangleMesh {
+		let mut normals = vec![Vec3::ZERO; positions.len()];
+		for face in indices {
+			let [a, b, c] = face.map(|i| positions[i]);
+			let face_normal = (b - a).cross(c - a);
+			for &i in face {
+				normals[i] += face_normal;
+			}
+		}
+		for normal in normals.iter_mut() {
+			if !normal.is_near_zero() { *normal = normal.unit(); }
+		}
+
+		let segments = 1usize << self.subdivision_level;
+		let mut displaced_positions = Vec::new();
+		let mut faces = Vec::new();
+		// source vertices and their weights, in index order, to the displaced vertex
+		let mut welded: HashMap<[(usize, usize); 3], u32> = HashMap::new();
+
+		for face in indices {
+			let corner = |k: usize| Vertex {
+				position: positions[face[k]],
+				normal: normals[face[k]],
+				uv: uvs.map_or((0.0, 0.0), |uvs| uvs[face[k]])
+			};
+			let corners = [corner(0), corner(1), corner(2)];
+
+			// grid of vertex indices, row i has segments - i + 1 entries
+			let mut grid = Vec::with_capacity((segments + 1) * (segments + 2) / 2);
+			for i in 0..=segments {
+				for j in 0..=(segments - i) {
+					let weights = [segments - i - j, i, j];
+
+					let mut key = [0, 1, 2].map(|k| if weights[k] == 0 { (usize::MAX, 0) } else { (face[k], weights[k]) });
+					key.sort();
+
+					let vertex = *welded.entry(key).or_insert_with(|| {
+						displaced_positions.push(self.displaced(&corners, weights, segments));
+						(displaced_positions.len() - 1) as u32
+					});
+					grid.push(vertex);
+				}
+			}
+
+			let index = |i: usize, j: usize| i * (segments + 1) - i * (i.saturating_sub(1)) / 2 + j;
+
+			for i in 0..segments {
+				for j in 0..(segments - i) {
+					let p0 = grid[index(i, j)];
+					let p1 = grid[index(i + 1, j)];
+					let p2 = grid[index(i, j + 1)];
+					faces.push([p0, p1, p2]);
+
+					if j + 1 < segments - i {
+						let p3 = grid[index(i + 1, j + 1)];
+						faces.push([p1, p3, p2]);
+					}
+				}
+			}
+		}
+
+		let settings = MeshSettings { padding: self.bounds_padding, ..MeshSettings::default() };
+		TriangleMesh::with_settings(MeshBuffers::new(displaced_positions, faces), material, settings)
+	}
+
+	// Interpolates the corners with integer weights summing to `segments` and displaces the result
+	fn displaced(&self, corners: &[Vertex; 3], weights: [usize; 3], segments: usize) -> Vec3 {
+		let mut position = Vec3::ZERO;
+		let mut normal = Vec3::ZERO;
+		let mut uv = (0.0, 0.0);
+
+		for (corner, weight) in corners.iter().zip(weights) {
+			let w = weight as f64 / segments as f64;
+			position += w * corner.position;
+			normal += w * corner.normal;
+			uv.0 += w * corner.uv.0;
+			uv.1 += w * corner.uv.1;
+		}
+
+		if !normal.is_near_zero() { normal = normal.unit(); }
+
+		let value = self.texture.value_at(uv.0, uv.1, position, normal);
+		let height = (value.x + value.y + value.z) / 3.0;
+
+		position + self.scale * height * normal
+	}
+}
+
+struct Vertex {
+	position: Vec3,
+	normal: Vec3,
+	uv: (f64, f64)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hittable::hittable::Hittable;
+	use crate::material::Lambertian;
+	use crate::ray::Ray;
+	use crate::texture::texture::{NoiseTexture, SolidColorTexture};
+	use crate::util::interval::Interval;
+
+	fn displacement(texture: Arc<dyn Texture>) -> Displacement {
+		Displacement { texture, scale: 0.5, subdivision_level: 3, bounds_padding: 0.0 }
+	}
+
+	fn unit_quad(displacement: &Displacement) -> TriangleMesh {
+		let material = Arc::new(Lambertian::from_color(Vec3::new(0.5, 0.5, 0.5)));
+		displacement.quad(Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), material)
+	}
+
+	fn hit_from_above(mesh: &TriangleMesh, x: f64, y: f64) -> Option<f64> {
+		let ray = Ray::new(Vec3::new(x, y, 10.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+		mesh.hit(ray, Interval::new(0.001, f64::INFINITY)).map(|hit| hit.position.z)
+	}
+
+	#[test]
+	fn constant_height_moves_along_normal() {
+		let mesh = unit_quad(&displacement(Arc::new(SolidColorTexture::from_rgb(0.4, 0.4, 0.4))));
+		assert_eq!(mesh.face_count(), 2 * 64);
+
+		for (x, y) in [(0.1, 0.2), (0.5, 0.5), (0.93, 0.71), (0.3, 0.9)] {
+			let z = hit_from_above(&mesh, x, y).unwrap();
+			assert!((z - 0.5 * 0.4).abs() < 1e-12);
+		}
+	}
+
+	#[test]
+	fn shared_edges_stay_closed() {
+		// rays along the diagonal both faces of the quad are split from
+		let mesh = unit_quad(&displacement(Arc::new(NoiseTexture::new(7.0))));
+		for step in 1..1000 {
+			let s = step as f64 / 1000.0;
+			assert!(hit_from_above(&mesh, s, s).is_some(), "ray through ({}, {}) slipped through", s, s);
+		}
+	}
+}
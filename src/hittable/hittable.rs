@@ -3,7 +3,10 @@ use crate::ray::Ray;
 use std::rc::Rc;
 use std::sync::Arc;
 use crate::AABB::AABB;
+use crate::ONB::OrthonormalBasis;
+use crate::texture::texture::UVDifferentials;
 use crate::util::interval::Interval;
+use crate::util::transform::{AnimatedTransform, Transform};
 use crate::util::util::deg_to_rad;
 use crate::util::vec3::Vec3;
 
@@ -12,14 +15,25 @@ pub trait Hittable: Send + Sync {
 	fn bounding_box(&self) -> AABB;
 }
 
+#[derive(Clone)]
 pub struct HitRecord {
 	pub t: f64,
 	pub position: Vec3,
+	// shading normal, interpolated or perturbed, always on the side of the geometric normal
 	pub normal: Vec3,
+	// true surface normal, for offsets and deciding which side was hit
+	pub geometric_normal: Vec3,
 	pub is_front_face: bool,
 	pub material: Arc<dyn Material>,
 	pub u: f64,
-	pub v: f64
+	pub v: f64,
+
+	// surface derivatives along u and v, forming the shading frame with the normal
+	pub dpdu: Vec3,
+	pub dpdv: Vec3,
+
+	// texture footprint, only known for rays carrying differentials, and the vertex color of mesh hits
+	pub uv_differentials: UVDifferentials
 }
 
 impl HitRecord {
@@ -35,9 +49,89 @@ impl HitRecord {
 		let is_front_face = ray.direction.dot(normal) < 0.0;
 		let flipped_normal = if is_front_face { normal } else { -normal };
 
+		// arbitrary frame around the normal until the hittable provides its own
+		let basis = OrthonormalBasis::new(normal);
+
 		HitRecord {
 			t, position, is_front_face, material, u, v,
-			normal: flipped_normal
+			normal: flipped_normal,
+			geometric_normal: flipped_normal,
+			dpdu: basis.u,
+			dpdv: basis.v,
+			uv_differentials: UVDifferentials::ZERO
+		}
+	}
+
+	pub fn with_tangents(mut self, dpdu: Vec3, dpdv: Vec3) -> Self {
+		self.dpdu = dpdu;
+		self.dpdv = dpdv;
+		self
+	}
+
+	// Handed on to textures along with the footprint
+	pub fn with_vertex_color(mut self, color: Vec3) -> Self {
+		self.uv_differentials.vertex_color = Some(color);
+		self
+	}
+
+	// Whether `direction` leaves on the side the ray arrived from. Judged by the true surface, since
+	// shading normals can tilt reflections below it or refractions above it.
+	pub fn is_on_incident_side(&self, direction: Vec3) -> bool {
+		direction.dot(self.geometric_normal) > 0.0
+	}
+
+	// Replaces the shading normal, flipped onto the side of the geometric normal facing the ray
+	pub fn with_shading_normal(mut self, normal: Vec3) -> Self {
+		if normal.is_near_zero() { return self; }
+
+		let shading_normal = normal.unit();
+		self.normal = if shading_normal.dot(self.geometric_normal) < 0.0 { -shading_normal } else { shading_normal };
+		self
+	}
+
+	// Projects the differential rays onto the tangent plane at the hit and expresses
+	// the offsets in (u, v) via least squares on dpdu and dpdv.
+	pub fn compute_differentials(&mut self, ray: &Ray) {
+		let vertex_color = self.uv_differentials.vertex_color;
+		self.uv_differentials = UVDifferentials { vertex_color, ..UVDifferentials::ZERO };
+		let Some(differentials) = ray.differentials else { return; };
+
+		// the true surface, shading normals are interpolated or perturbed by bump maps
+		let normal = self.geometric_normal;
+		let d = normal.dot(self.position);
+		let tangent_plane_hit = |origin: Vec3, direction: Vec3| {
+			let denominator = normal.dot(direction);
+			if denominator.abs() < 1e-12 { return None; }
+			let t = (d - normal.dot(origin)) / denominator;
+			Some(origin + t * direction)
+		};
+
+		let Some(px) = tangent_plane_hit(differentials.rx_origin, differentials.rx_direction) else { return; };
+		let Some(py) = tangent_plane_hit(differentials.ry_origin, differentials.ry_direction) else { return; };
+		let dpdx = px - self.position;
+		let dpdy = py - self.position;
+
+		let a_00 = self.dpdu.dot(self.dpdu);
+		let a_01 = self.dpdu.dot(self.dpdv);
+		let a_11 = self.dpdv.dot(self.dpdv);
+		let det = a_00 * a_11 - a_01 * a_01;
+		if det.abs() < 1e-16 { return; }
+		let inv_det = 1.0 / det;
+
+		let solve = |dp: Vec3| {
+			let b_0 = self.dpdu.dot(dp);
+			let b_1 = self.dpdv.dot(dp);
+			(
+				(a_11 * b_0 - a_01 * b_1) * inv_det,
+				(a_00 * b_1 - a_01 * b_0) * inv_det
+			)
+		};
+
+		let (du_dx, dv_dx) = solve(dpdx);
+		let (du_dy, dv_dy) = solve(dpdy);
+
+		if du_dx.is_finite() && dv_dx.is_finite() && du_dy.is_finite() && dv_dy.is_finite() {
+			self.uv_differentials = UVDifferentials { du_dx, dv_dx, du_dy, dv_dy, dp_dx: dpdx, dp_dy: dpdy, vertex_color };
 		}
 	}
 }
@@ -204,6 +298,24 @@ impl Hittable for RotateY {
 				(-self.sin_theta * hit_record.normal.x) + (self.cos_theta * hit_record.normal.z)
 			);
 
+			hit_record.geometric_normal = Vec3::new(
+				(self.cos_theta * hit_record.geometric_normal.x) + (self.sin_theta * hit_record.geometric_normal.z),
+				hit_record.geometric_normal.y,
+				(-self.sin_theta * hit_record.geometric_normal.x) + (self.cos_theta * hit_record.geometric_normal.z)
+			);
+
+			hit_record.dpdu = Vec3::new(
+				(self.cos_theta * hit_record.dpdu.x) + (self.sin_theta * hit_record.dpdu.z),
+				hit_record.dpdu.y,
+				(-self.sin_theta * hit_record.dpdu.x) + (self.cos_theta * hit_record.dpdu.z)
+			);
+
+			hit_record.dpdv = Vec3::new(
+				(self.cos_theta * hit_record.dpdv.x) + (self.sin_theta * hit_record.dpdv.z),
+				hit_record.dpdv.y,
+				(-self.sin_theta * hit_record.dpdv.x) + (self.cos_theta * hit_record.dpdv.z)
+			);
+
 			return Some(hit_record);
 		}
 
@@ -213,4 +325,72 @@ impl Hittable for RotateY {
 	fn bounding_box(&self) -> AABB {
 		self.bbox
 	}
-}
\ No newline at end of file
+}
+
+// Any affine transform, e.g. rotations about other axes, scaling or shearing, in a single
+// hit call however many steps it was composed from
+pub struct TransformInstance {
+	object: Box<dyn Hittable>,
+	transform: Transform,
+	bbox: AABB
+}
+
+impl TransformInstance {
+	pub fn new(object: Box<dyn Hittable>, transform: Transform) -> Self {
+		let bbox = transform.bounding_box(object.bounding_box());
+		TransformInstance { object, transform, bbox }
+	}
+}
+
+impl Hittable for TransformInstance {
+	fn hit(&self, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
+		transformed_hit(self.object.as_ref(), &self.transform, ray, ray_t)
+	}
+
+	fn bounding_box(&self) -> AABB {
+		self.bbox
+	}
+}
+
+// Transform following keyframes, evaluated at each ray's time for motion blur
+pub struct AnimatedInstance {
+	object: Box<dyn Hittable>,
+	animation: AnimatedTransform,
+	bbox: AABB
+}
+
+impl AnimatedInstance {
+	pub fn new(object: Box<dyn Hittable>, animation: AnimatedTransform) -> Self {
+		let bbox = animation.bounding_box(object.bounding_box());
+		AnimatedInstance { object, animation, bbox }
+	}
+}
+
+impl Hittable for AnimatedInstance {
+	fn hit(&self, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
+		transformed_hit(self.object.as_ref(), &self.animation.at(ray.time), ray, ray_t)
+	}
+
+	fn bounding_box(&self) -> AABB {
+		self.bbox
+	}
+}
+
+// Intersects in object space. The direction is not normalized so t carries over unchanged.
+pub(crate) fn transformed_hit(object: &dyn Hittable, transform: &Transform, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
+	let object_ray = Ray::new(
+		transform.inverse_point(ray.origin),
+		transform.inverse_vector(ray.direction),
+		ray.time
+	);
+
+	let mut hit_record = object.hit(object_ray, ray_t)?;
+
+	hit_record.position = ray.at(hit_record.t);
+	hit_record.normal = transform.normal(hit_record.normal).unit();
+	hit_record.geometric_normal = transform.normal(hit_record.geometric_normal).unit();
+	hit_record.dpdu = transform.vector(hit_record.dpdu);
+	hit_record.dpdv = transform.vector(hit_record.dpdv);
+
+	Some(hit_record)
+}
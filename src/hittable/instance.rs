@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use crate::AABB::AABB;
+use crate::hittable::hittable::{transformed_hit, HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::util::interval::Interval;
+use crate::util::transform::Transform;
+
+// One placement of geometry shared between many instances, e.g. a mesh BVH,
+// so a thousand copies cost a thousand transforms instead of a thousand meshes
+pub struct Instance {
+	object: Arc<dyn Hittable>,
+	transform: Transform,
+	// replaces the material of every hit when set
+	material: Option<Arc<dyn Material>>,
+	bbox: AABB
+}
+
+impl Instance {
+	pub fn new(object: Arc<dyn Hittable>, transform: Transform) -> Self {
+		let bbox = transform.bounding_box(object.bounding_box());
+		Instance { object, transform, material: None, bbox }
+	}
+
+	pub fn with_material(mut self, material: Arc<dyn Material>) -> Self {
+		self.material = Some(material);
+		self
+	}
+}
+
+impl Hittable for Instance {
+	fn hit(&self, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
+		let mut hit_record = transformed_hit(self.object.as_ref(), &self.transform, ray, ray_t)?;
+
+		if let Some(material) = &self.material {
+			hit_record.material = material.clone();
+		}
+
+		Some(hit_record)
+	}
+
+	fn bounding_box(&self) -> AABB {
+		self.bbox
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hittable::sphere::Sphere;
+	use crate::material::Lambertian;
+	use crate::util::vec3::Vec3;
+
+	fn hit_along_z(object: &dyn Hittable, x: f64) -> HitRecord {
+		let ray = Ray::new(Vec3::new(x, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+		object.hit(ray, Interval::new(0.001, f64::INFINITY)).unwrap()
+	}
+
+	#[test]
+	fn material_override_leaves_shared_geometry_alone() {
+		let red: Arc<dyn Material> = Arc::new(Lambertian::from_color(Vec3::new(0.8, 0.1, 0.1)));
+		let blue: Arc<dyn Material> = Arc::new(Lambertian::from_color(Vec3::new(0.1, 0.1, 0.8)));
+		let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new_stationary(Vec3::ZERO, 1.0, red.clone()));
+
+		let plain = Instance::new(sphere.clone(), Transform::IDENTITY);
+		let painted = Instance::new(sphere.clone(), Transform::translate(Vec3::new(3.0, 0.0, 0.0))).with_material(blue.clone());
+
+		let plain_hit = hit_along_z(&plain, 0.0);
+		let painted_hit = hit_along_z(&painted, 3.0);
+		assert!(Arc::ptr_eq(&plain_hit.material, &red));
+		assert!(Arc::ptr_eq(&painted_hit.material, &blue));
+
+		// both hit the same geometry, only moved
+		assert!((plain_hit.t - painted_hit.t).abs() < 1e-12);
+		assert!(Arc::ptr_eq(&hit_along_z(sphere.as_ref(), 0.0).material, &red));
+	}
+}
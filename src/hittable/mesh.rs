@@ -0,0 +1,234 @@
+use std::sync::Arc;
+use crate::AABB::AABB;
+use crate::hittable::alpha::AlphaMask;
+use crate::hittable::BVH::BVHNode;
+use crate::hittable::hittable::{HitRecord, Hittable, HittableList};
+use crate::hittable::triangle::{intersect, shade, uv_tangents, Culling, Surface};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::util::interval::Interval;
+use crate::util::vec3::Vec3;
+
+// Vertex attributes shared by all faces of a mesh. Faces wind counter-clockwise seen from the front.
+pub struct MeshBuffers {
+	pub positions: Vec<Vec3>,
+	pub normals: Option<Vec<Vec3>>,
+	pub uvs: Option<Vec<(f64, f64)>>,
+	// glTF style: the direction of increasing u and the sign of normal x tangent along increasing v
+	pub tangents: Option<Vec<(Vec3, f64)>>,
+	// linear RGB, handed to textures through the hit, see VertexColorTexture
+	pub colors: Option<Vec<Vec3>>,
+	pub faces: Vec<[u32; 3]>
+}
+
+impl MeshBuffers {
+	pub fn new(positions: Vec<Vec3>, faces: Vec<[u32; 3]>) -> Self {
+		MeshBuffers { positions, normals: None, uvs: None, tangents: None, colors: None, faces }
+	}
+
+	fn memory_usage(&self) -> usize {
+		self.positions.capacity() * size_of::<Vec3>()
+			+ self.normals.as_ref().map_or(0, |normals| normals.capacity() * size_of::<Vec3>())
+			+ self.uvs.as_ref().map_or(0, |uvs| uvs.capacity() * size_of::<(f64, f64)>())
+			+ self.tangents.as_ref().map_or(0, |tangents| tangents.capacity() * size_of::<(Vec3, f64)>())
+			+ self.colors.as_ref().map_or(0, |colors| colors.capacity() * size_of::<Vec3>())
+			+ self.faces.capacity() * size_of::<[u32; 3]>()
+	}
+}
+
+// Indexed triangles with one material, much lighter than a Triangle per face
+// since faces only hold an index into the shared buffers. Has its own BVH over the faces.
+pub struct TriangleMesh {
+	mesh: Arc<Mesh>,
+	bvh: Box<dyn Hittable>
+}
+
+struct Mesh {
+	buffers: MeshBuffers,
+	material: Arc<dyn Material>,
+	settings: MeshSettings
+}
+
+// Per-mesh options applying to every face
+pub struct MeshSettings {
+	pub alpha_mask: Option<AlphaMask>,
+	pub culling: Culling,
+	// extra room added to each face's bounding box
+	pub padding: f64
+}
+
+impl Default for MeshSettings {
+	fn default() -> Self {
+		MeshSettings { alpha_mask: None, culling: Culling::NONE, padding: 0.0 }
+	}
+}
+
+impl TriangleMesh {
+	pub fn new(buffers: MeshBuffers, material: Arc<dyn Material>) -> Self {
+		Self::with_settings(buffers, material, MeshSettings::default())
+	}
+
+	pub fn with_settings(buffers: MeshBuffers, material: Arc<dyn Material>, settings: MeshSettings) -> Self {
+		let mesh = Arc::new(Mesh { buffers, material, settings });
+
+		let faces: Vec<Box<dyn Hittable>> = (0..mesh.buffers.faces.len())
+			.map(|face| Box::new(MeshFace { mesh: mesh.clone(), face: face as u32 }) as Box<dyn Hittable>)
+			.collect();
+
+		// the tree cannot be built from nothing
+		let bvh: Box<dyn Hittable> = if faces.is_empty() {
+			Box::new(HittableList::new())
+		} else {
+			BVHNode::construct_tree(faces)
+		};
+
+		TriangleMesh { mesh, bvh }
+	}
+
+	pub fn face_count(&self) -> usize {
+		self.mesh.buffers.faces.len()
+	}
+
+	// Bytes held by the shared buffers, not counting the BVH
+	pub fn memory_usage(&self) -> usize {
+		self.mesh.buffers.memory_usage()
+	}
+}
+
+impl Hittable for TriangleMesh {
+	fn hit(&self, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
+		self.bvh.hit(ray, ray_t)
+	}
+
+	fn bounding_box(&self) -> AABB {
+		self.bvh.bounding_box()
+	}
+}
+
+// One face of a TriangleMesh, a BVH leaf
+struct MeshFace {
+	mesh: Arc<Mesh>,
+	face: u32
+}
+
+impl MeshFace {
+	fn indices(&self) -> [usize; 3] {
+		self.mesh.buffers.faces[self.face as usize].map(|index| index as usize)
+	}
+
+	fn corners(&self) -> [Vec3; 3] {
+		self.indices().map(|index| self.mesh.buffers.positions[index])
+	}
+}
+
+impl Hittable for MeshFace {
+	fn hit(&self, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
+		let corners = self.corners();
+		let (t, u, v) = intersect(corners, ray, ray_t, self.mesh.settings.culling)?;
+
+		let indices = self.indices();
+		let buffers = &self.mesh.buffers;
+		let [p_a, p_b, p_c] = corners;
+
+		let uvs = buffers.uvs.as_ref().map(|uvs| indices.map(|index| uvs[index]));
+
+		// u weights c and v weights b, the uvs give better tangents where there are some
+		let (dpdu, dpdv) = uvs.and_then(|uvs| uv_tangents(corners, uvs)).unwrap_or((p_c - p_a, p_b - p_a));
+
+		let surface = Surface {
+			normal: (p_b - p_a).cross(p_c - p_a).unit(),
+			dpdu,
+			dpdv,
+			uvs,
+			normals: buffers.normals.as_ref().map(|normals| indices.map(|index| normals[index])),
+			tangents: buffers.tangents.as_ref().map(|tangents| indices.map(|index| tangents[index])),
+			colors: buffers.colors.as_ref().map(|colors| indices.map(|index| colors[index])),
+			material: &self.mesh.material,
+			alpha_mask: self.mesh.settings.alpha_mask.as_ref()
+		};
+		shade(surface, ray, t, u, v)
+	}
+
+	fn bounding_box(&self) -> AABB {
+		let [a, b, c] = self.corners();
+		AABB::from_AABB_pair(AABB::from_corners(a, b), AABB::from_corners(a, c)).pad(self.mesh.settings.padding)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hittable::triangle::Triangle;
+	use crate::material::Lambertian;
+	use crate::texture::texture::{Texture, VertexColorTexture};
+
+	fn gray() -> Arc<dyn Material> {
+		Arc::new(Lambertian::from_color(Vec3::new(0.5, 0.5, 0.5)))
+	}
+
+	// Right triangle in the xy plane facing +z
+	fn triangle_buffers() -> MeshBuffers {
+		MeshBuffers::new(
+			vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)],
+			vec![[0, 1, 2]]
+		)
+	}
+
+	fn hit_from_above(object: &dyn Hittable, x: f64, y: f64) -> Option<HitRecord> {
+		object.hit(Ray::new(Vec3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0), Interval::new(0.001, f64::INFINITY))
+	}
+
+	#[test]
+	fn vertex_colors_reach_textures() {
+		let mut buffers = triangle_buffers();
+		buffers.colors = Some(vec![Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)]);
+		let mesh = TriangleMesh::new(buffers, gray());
+
+		let mut hit = hit_from_above(&mesh, 0.25, 0.5).unwrap();
+		// differentials are worked out after the hit and must keep the color
+		hit.compute_differentials(&Ray::new(Vec3::new(0.25, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0));
+
+		let texture = VertexColorTexture::new(Vec3::ZERO);
+		let color = texture.filtered_value_at(hit.u, hit.v, hit.position, hit.normal, hit.uv_differentials);
+		assert!((color - Vec3::new(0.25, 0.25, 0.5)).length() < 1e-12);
+
+		// meshes without colors fall back
+		let hit = hit_from_above(&TriangleMesh::new(triangle_buffers(), gray()), 0.25, 0.5).unwrap();
+		let fallback = Vec3::new(0.3, 0.3, 0.3);
+		let color = VertexColorTexture::new(fallback).filtered_value_at(hit.u, hit.v, hit.position, hit.normal, hit.uv_differentials);
+		assert!((color - fallback).length() < 1e-12);
+	}
+
+	#[test]
+	fn faces_match_triangles() {
+		let positions = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.2, 0.1), Vec3::new(-0.1, 1.0, -0.2)];
+		let normals = [Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.6, 0.0, 0.8), Vec3::new(0.0, 0.6, 0.8)];
+		let uvs = [(0.0, 0.0), (2.0, 0.5), (0.5, 3.0)];
+
+		let mut buffers = MeshBuffers::new(positions.to_vec(), vec![[0, 1, 2]]);
+		buffers.normals = Some(normals.to_vec());
+		buffers.uvs = Some(uvs.to_vec());
+		let mesh = TriangleMesh::new(buffers, gray());
+
+		let [a, b, c] = positions;
+		let triangle = Triangle::new(a, b, c, gray())
+			.with_uvs(uvs[0], uvs[1], uvs[2])
+			.with_normals(normals[0], normals[1], normals[2]);
+
+		for (x, y) in [(0.1, 0.1), (0.5, 0.3), (0.2, 0.7), (0.9, 0.9)] {
+			match (hit_from_above(&mesh, x, y), hit_from_above(&triangle, x, y)) {
+				(Some(face_hit), Some(triangle_hit)) => {
+					assert!((face_hit.t - triangle_hit.t).abs() < 1e-12);
+					assert!((face_hit.u - triangle_hit.u).abs() < 1e-12);
+					assert!((face_hit.v - triangle_hit.v).abs() < 1e-12);
+					assert!((face_hit.normal - triangle_hit.normal).length() < 1e-12);
+					assert!((face_hit.geometric_normal - triangle_hit.geometric_normal).length() < 1e-12);
+					assert!((face_hit.dpdu - triangle_hit.dpdu).length() < 1e-12);
+					assert!((face_hit.dpdv - triangle_hit.dpdv).length() < 1e-12);
+				},
+				(None, None) => (),
+				_ => panic!("mesh and triangle disagree at ({}, {})", x, y)
+			}
+		}
+	}
+}
@@ -1,7 +1,15 @@
+pub mod alpha;
 pub mod BVH;
+pub mod cone;
+pub mod cylinder;
+pub mod disk;
+pub mod displacement;
 pub mod hittable;
+pub mod instance;
+pub mod mesh;
 pub mod quad;
 pub mod sphere;
+pub mod torus;
 pub mod volume;
 mod plane;
 pub mod triangle;
\ No newline at end of file
@@ -2,6 +2,7 @@ use std::rc::Rc;
 use std::sync::Arc;
 use log::warn;
 use crate::AABB::AABB;
+use crate::hittable::alpha::AlphaMask;
 use crate::hittable::hittable::{HitRecord, Hittable, HittableList};
 use crate::hittable::plane::Plane;
 use crate::material::Material;
@@ -18,6 +19,7 @@ pub struct Quad {
 	w: Vec3,
 
 	material: Arc<dyn Material>,
+	alpha_mask: Option<AlphaMask>,
 	bbox: AABB,
 }
 
@@ -34,7 +36,12 @@ impl Quad {
 
 		let plane = Plane::new(normal, q);
 
-		Quad { plane, q, u, v, w, material, bbox }
+		Quad { plane, q, u, v, w, material, alpha_mask: None, bbox }
+	}
+
+	pub fn with_alpha_mask(mut self, alpha_mask: AlphaMask) -> Self {
+		self.alpha_mask = Some(alpha_mask);
+		self
 	}
 
 	pub fn is_in_mandelbrot(alpha: f64, beta: f64, max_iterations: usize) -> bool {
@@ -92,6 +99,10 @@ impl Hittable for Quad {
 			return None;
 		}
 
+		if let Some(alpha_mask) = &self.alpha_mask {
+			if alpha_mask.is_transparent(alpha, beta, intersection, self.plane.normal) { return None; }
+		}
+
 		// cool mandelbrot shape
 		// let u = -2.0 + alpha * 3.0;
 		// let v = -1.5 + beta * 3.0;
@@ -105,7 +116,7 @@ impl Hittable for Quad {
 			self.material.clone(),
 			alpha,
 			beta
-		))
+		).with_tangents(self.u, self.v))
 	}
 
 	fn bounding_box(&self) -> AABB {
@@ -2,6 +2,7 @@ use std::f64::consts::PI;
 use std::rc::Rc;
 use std::sync::Arc;
 use crate::AABB::AABB;
+use crate::hittable::alpha::AlphaMask;
 use crate::hittable::hittable::{HitRecord, Hittable};
 use crate::material::Material;
 use crate::ray::Ray;
@@ -14,6 +15,7 @@ pub struct Sphere {
 	is_moving: bool,
 	radius: f64,
 	material: Arc<dyn Material>,
+	alpha_mask: Option<AlphaMask>,
 	bbox: AABB
 }
 
@@ -28,6 +30,7 @@ impl Sphere {
 			is_moving: false,
 			radius,
 			material,
+			alpha_mask: None,
 			bbox
 		}
 	}
@@ -44,10 +47,16 @@ impl Sphere {
 			is_moving: true,
 			radius,
 			material,
+			alpha_mask: None,
 			bbox: bbox_full
 		}
 	}
 
+	pub fn with_alpha_mask(mut self, alpha_mask: AlphaMask) -> Self {
+		self.alpha_mask = Some(alpha_mask);
+		self
+	}
+
 	fn current_center(&self, time: f64) -> Vec3 {
 		self.center_start + time * self.center_path
 	}
@@ -58,6 +67,21 @@ impl Sphere {
 
 		(phi / (2.0 * PI), theta / PI)
 	}
+
+	// Derivatives of the sphere_uv parameterization, p is relative to the center
+	fn sphere_tangents(p: Vec3) -> (Vec3, Vec3) {
+		let dpdu = 2.0 * PI * Vec3::new(p.z, 0.0, -p.x);
+
+		// distance from the pole axis, degenerate at the poles
+		let rho = f64::sqrt(p.x * p.x + p.z * p.z);
+		if rho < 1e-8 {
+			return (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+		}
+
+		let dpdv = PI * Vec3::new(-p.x * p.y / rho, rho, -p.y * p.z / rho);
+
+		(dpdu, dpdv)
+	}
 }
 
 impl Hittable for Sphere {
@@ -79,30 +103,33 @@ impl Hittable for Sphere {
 
 		let sqrt_d = f64::sqrt(discriminant);
 
-		// Find root in ray's range
-		let mut t = (h - sqrt_d) / a;
-		if !ray_t.surrounds(t) {
-			t = (h + sqrt_d) / a;
-			if !ray_t.surrounds(t) {
-				return None
-			}
-		}
+		// Check both roots in order, the far side is visible through cutouts in the near side
+		for t in [(h - sqrt_d) / a, (h + sqrt_d) / a] {
+			if !ray_t.surrounds(t) { continue; }
 
-		let hit_pos = ray.at(t);
-		let outward_normal = (hit_pos - center) / self.radius;
+			let hit_pos = ray.at(t);
+			let outward_normal = (hit_pos - center) / self.radius;
 
+			let (u, v) = Self::sphere_uv(outward_normal);
 
-		let (u, v) = Self::sphere_uv(outward_normal);
+			if let Some(alpha_mask) = &self.alpha_mask {
+				if alpha_mask.is_transparent(u, v, hit_pos, outward_normal) { continue; }
+			}
+
+			let (dpdu, dpdv) = Self::sphere_tangents(hit_pos - center);
+
+			return Some(HitRecord::new(
+				ray,
+				t,
+				hit_pos,
+				outward_normal,
+				self.material.clone(),
+				u,
+				v
+			).with_tangents(dpdu, dpdv));
+		}
 
-		Some(HitRecord::new(
-			ray,
-			t,
-			hit_pos,
-			outward_normal,
-			self.material.clone(),
-			u,
-			v
-		))
+		None
 	}
 
 	fn bounding_box(&self) -> AABB {
@@ -0,0 +1,216 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+use crate::AABB::AABB;
+use crate::hittable::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::util::interval::Interval;
+use crate::util::vec3::Vec3;
+
+// Ring lying flat around `center`, other orientations go through a TransformInstance.
+// u goes around the y axis like on a sphere, v goes around the tube starting on its inner side.
+pub struct Torus {
+	center: Vec3,
+	// from the center to the middle of the tube
+	major_radius: f64,
+	// of the tube
+	minor_radius: f64,
+	material: Arc<dyn Material>,
+	bbox: AABB
+}
+
+impl Torus {
+	pub fn new(center: Vec3, major_radius: f64, minor_radius: f64, material: Arc<dyn Material>) -> Self {
+		let extent = Vec3::new(major_radius + minor_radius, minor_radius, major_radius + minor_radius);
+		let bbox = AABB::from_corners(center - extent, center + extent);
+
+		Torus { center, major_radius, minor_radius, material, bbox }
+	}
+
+	// Range of t inside the sphere enclosing the torus
+	fn bounding_sphere_interval(&self, ray: Ray) -> Option<Interval> {
+		let oc = self.center - ray.origin;
+		let radius = self.major_radius + self.minor_radius;
+		let a = ray.direction.length_squared();
+		let h = ray.direction.dot(oc);
+		let c = oc.length_squared() - radius * radius;
+		let discriminant = h * h - a * c;
+
+		if discriminant < 0.0 {
+			return None
+		}
+
+		let sqrt_d = f64::sqrt(discriminant);
+		Some(Interval::new((h - sqrt_d) / a, (h + sqrt_d) / a))
+	}
+}
+
+impl Hittable for Torus {
+	fn hit(&self, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
+		let sphere_t = self.bounding_sphere_interval(ray)?;
+		// the sphere touches the torus around its outer equator, starting a little outside
+		// keeps roots there from sitting on the edge of the interval
+		let margin = 1e-6 * sphere_t.size();
+		let start = (sphere_t.min - margin).max(ray_t.min);
+		let end = sphere_t.max.min(ray_t.max);
+		if start >= end { return None; }
+
+		// solving from where the ray enters the bounding sphere keeps the coefficients small,
+		// far away origins otherwise lose most of the precision
+		let origin = ray.origin + start * ray.direction - self.center;
+		let direction = ray.direction;
+
+		// (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + z^2), expanded into a quartic in t
+		let (major_sq, minor_sq) = (self.major_radius * self.major_radius, self.minor_radius * self.minor_radius);
+		let dd = direction.length_squared();
+		let od = origin.dot(direction);
+		let e = origin.length_squared() + major_sq - minor_sq;
+		let planar_dd = direction.x * direction.x + direction.z * direction.z;
+		let planar_od = origin.x * direction.x + origin.z * direction.z;
+		let planar_oo = origin.x * origin.x + origin.z * origin.z;
+
+		let coefficients = [
+			e * e - 4.0 * major_sq * planar_oo,
+			4.0 * od * e - 8.0 * major_sq * planar_od,
+			4.0 * od * od + 2.0 * dd * e - 4.0 * major_sq * planar_dd,
+			4.0 * dd * od,
+			dd * dd
+		];
+
+		for root in polynomial_roots(&coefficients, Interval::new(0.0, end - start)) {
+			let t = start + root;
+			if !ray_t.surrounds(t) { continue; }
+
+			let position = ray.at(t);
+			let p = position - self.center;
+
+			let rho = f64::sqrt(p.x * p.x + p.z * p.z);
+			if rho < 1e-8 { continue; }
+			let radial = Vec3::new(p.x, 0.0, p.z) / rho;
+
+			// from the middle of the tube to the hit
+			let tube = p - self.major_radius * radial;
+			let outward_normal = tube / self.minor_radius;
+
+			let tube_angle = p.y.atan2(rho - self.major_radius);
+			let u = ((-p.z).atan2(p.x) + PI) / (2.0 * PI);
+			let v = (tube_angle + PI) / (2.0 * PI);
+
+			let dpdu = 2.0 * PI * Vec3::new(p.z, 0.0, -p.x);
+			let dpdv = 2.0 * PI * (Vec3::new(0.0, rho - self.major_radius, 0.0) - p.y * radial);
+
+			return Some(HitRecord::new(
+				ray,
+				t,
+				position,
+				outward_normal,
+				self.material.clone(),
+				u,
+				v
+			).with_tangents(dpdu, dpdv));
+		}
+
+		None
+	}
+
+	fn bounding_box(&self) -> AABB {
+		self.bbox
+	}
+}
+
+// Real roots inside the interval in ascending order, coefficients start at the constant term.
+// The roots of the derivative split the interval into monotonic pieces, each holding at most one root.
+fn polynomial_roots(coefficients: &[f64], interval: Interval) -> Vec<f64> {
+	let degree = coefficients.len() - 1;
+
+	if degree == 1 {
+		if coefficients[1] == 0.0 { return vec![]; }
+		let root = -coefficients[0] / coefficients[1];
+		return if interval.contains(root) { vec![root] } else { vec![] };
+	}
+
+	let derivative: Vec<f64> = coefficients.iter()
+		.enumerate()
+		.skip(1)
+		.map(|(power, coefficient)| power as f64 * coefficient)
+		.collect();
+
+	let mut bounds = vec![interval.min];
+	bounds.extend(polynomial_roots(&derivative, interval));
+	bounds.push(interval.max);
+
+	let evaluate = |x: f64| coefficients.iter().rev().fold(0.0, |sum, coefficient| sum * x + coefficient);
+
+	let mut roots = Vec::new();
+	for piece in bounds.windows(2) {
+		let (mut low, mut high) = (piece[0], piece[1]);
+		let low_positive = evaluate(low) > 0.0;
+		if low_positive == (evaluate(high) > 0.0) { continue; }
+
+		// bisection, 64 halvings exhaust the precision of any interval
+		for _ in 0..64 {
+			let middle = 0.5 * (low + high);
+			if (evaluate(middle) > 0.0) == low_positive {
+				low = middle;
+			} else {
+				high = middle;
+			}
+		}
+		roots.push(0.5 * (low + high));
+	}
+
+	roots
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::material::Lambertian;
+
+	fn assert_roots(found: Vec<f64>, expected: &[f64]) {
+		assert_eq!(found.len(), expected.len(), "{:?} != {:?}", found, expected);
+		for (root, expected) in found.iter().zip(expected) {
+			assert!((root - expected).abs() < 1e-9, "{:?} != {:?}", found, expected);
+		}
+	}
+
+	#[test]
+	fn roots_of_quartic() {
+		// (x - 1)(x - 2)(x - 3)(x - 4)
+		let coefficients = [24.0, -50.0, 35.0, -10.0, 1.0];
+		assert_roots(polynomial_roots(&coefficients, Interval::new(0.0, 10.0)), &[1.0, 2.0, 3.0, 4.0]);
+		assert_roots(polynomial_roots(&coefficients, Interval::new(1.5, 3.5)), &[2.0, 3.0]);
+	}
+
+	#[test]
+	fn roots_close_together() {
+		// (x - 0.5)(x - 0.501)(x^2 + 1)
+		let coefficients = [0.2505, -1.001, 1.2505, -1.001, 1.0];
+		assert_roots(polynomial_roots(&coefficients, Interval::new(0.0, 1.0)), &[0.5, 0.501]);
+	}
+
+	#[test]
+	fn no_real_roots() {
+		// x^4 + x^2 + 1
+		assert_roots(polynomial_roots(&[1.0, 0.0, 1.0, 0.0, 1.0], Interval::new(-10.0, 10.0)), &[]);
+		assert_roots(polynomial_roots(&[2.0, 0.0], Interval::new(-10.0, 10.0)), &[]);
+	}
+
+	#[test]
+	fn ray_through_tube_and_hole() {
+		let torus = Torus::new(Vec3::ZERO, 2.0, 0.5, Arc::new(Lambertian::from_color(Vec3::new(0.5, 0.5, 0.5))));
+		let ray_t = Interval::new(0.001, f64::INFINITY);
+
+		// along the x axis the ray crosses the tube at 1.5 and 2.5 from the center
+		let hit = torus.hit(Ray::new(Vec3::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0), ray_t).unwrap();
+		assert!((hit.t - 7.5).abs() < 1e-9);
+		assert!((hit.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-9);
+
+		// straight down through the hole
+		assert!(torus.hit(Ray::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0), ray_t).is_none());
+
+		// down onto the top of the tube
+		let hit = torus.hit(Ray::new(Vec3::new(0.0, 10.0, 2.0), Vec3::new(0.0, -1.0, 0.0), 0.0), ray_t).unwrap();
+		assert!((hit.t - 9.5).abs() < 1e-9);
+	}
+}
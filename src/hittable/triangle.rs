@@ -1,5 +1,6 @@
 use std::sync::Arc;
 use crate::AABB::AABB;
+use crate::hittable::alpha::AlphaMask;
 use crate::hittable::hittable::{HitRecord, Hittable};
 use crate::hittable::plane::Plane;
 use crate::material::Material;
@@ -7,67 +8,317 @@ use crate::ray::Ray;
 use crate::util::interval::Interval;
 use crate::util::vec3::Vec3;
 
+// Which side of a triangle rays pass through unseen. The front is the side
+// a, b and c appear counter-clockwise from, where the normal points.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Culling {
+    NONE,
+    BACK,
+    FRONT
+}
+
 pub struct Triangle {
     normal: Vec3,
     a: Vec3,
     b: Vec3,
     c: Vec3,
     material: Arc<dyn Material>,
+    alpha_mask: Option<AlphaMask>,
+    culling: Culling,
+    // texture coordinates at a, b and c, the raw barycentrics are used without them
+    uvs: Option<[(f64, f64); 3]>,
+    // vertex normals at a, b and c for smooth shading, the face normal is used without them
+    normals: Option<[Vec3; 3]>,
+    dpdu: Vec3,
+    dpdv: Vec3,
     bbox: AABB,
 }
 
 impl Triangle {
     pub fn new(a: Vec3, b: Vec3, c: Vec3, material: Arc<dyn Material>) -> Self {
-        let normal = (b - a).cross(c - a);
+        // degenerate triangles are never hit, so their NaN normal is never read
+        let normal = (b - a).cross(c - a).unit();
 
         let bbox_diag1 = AABB::from_corners(a, b);
         let bbox_diag2 = AABB::from_corners(a, c);
         let bbox = AABB::from_AABB_pair(bbox_diag1, bbox_diag2);
 
-        Triangle { normal, a, b, c, material, bbox }
+        // u weights c and v weights b in the barycentrics returned by hit
+        let dpdu = c - a;
+        let dpdv = b - a;
+
+        Triangle { normal, a, b, c, material, alpha_mask: None, culling: Culling::NONE, uvs: None, normals: None, dpdu, dpdv, bbox }
+    }
+
+    pub fn with_uvs(mut self, uv_a: (f64, f64), uv_b: (f64, f64), uv_c: (f64, f64)) -> Self {
+        self.uvs = Some([uv_a, uv_b, uv_c]);
+
+        // degenerate mappings keep the edge tangents
+        if let Some((dpdu, dpdv)) = uv_tangents([self.a, self.b, self.c], [uv_a, uv_b, uv_c]) {
+            self.dpdu = dpdu;
+            self.dpdv = dpdv;
+        }
+
+        self
+    }
+
+    pub fn with_normals(mut self, normal_a: Vec3, normal_b: Vec3, normal_c: Vec3) -> Self {
+        self.normals = Some([normal_a, normal_b, normal_c]);
+        self
+    }
+
+    pub fn with_alpha_mask(mut self, alpha_mask: AlphaMask) -> Self {
+        self.alpha_mask = Some(alpha_mask);
+        self
+    }
+
+    // Triangles are hit from both sides unless culled
+    pub fn with_culling(mut self, culling: Culling) -> Self {
+        self.culling = culling;
+        self
     }
 
-    const EPSILON: f64 = 1e-8;
 }
 
-impl Hittable for Triangle {
-    fn hit(&self, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
-        let edge_1 = self.c - self.a;
-        let edge_2 = self.b - self.a;
+// Watertight ray/triangle test (Woop et al. 2013): shared edges are evaluated identically
+// for both neighbours, so rays cannot slip through the cracks between them.
+// Returns t and the barycentrics, u weighting c and v weighting b.
+pub(crate) fn intersect([a, b, c]: [Vec3; 3], ray: Ray, ray_t: Interval, culling: Culling) -> Option<(f64, f64, f64)> {
+    if culling != Culling::NONE {
+        let facing = ray.direction.dot((b - a).cross(c - a));
+        let culled = match culling {
+            Culling::BACK => facing >= 0.0,
+            Culling::FRONT => facing <= 0.0,
+            Culling::NONE => false
+        };
+        if culled { return None; }
+    }
+
+    let direction = [ray.direction.x, ray.direction.y, ray.direction.z];
+
+    // make z the dominant axis of the direction, swapping x and y to keep the winding
+    let abs = direction.map(f64::abs);
+    let kz = if abs[0] > abs[1] {
+        if abs[0] > abs[2] { 0 } else { 2 }
+    } else if abs[1] > abs[2] { 1 } else { 2 };
+    let (mut kx, mut ky) = ((kz + 1) % 3, (kz + 2) % 3);
+    if direction[kz] < 0.0 { (kx, ky) = (ky, kx); }
+
+    if direction[kz] == 0.0 { return None; }
+
+    // shear so the ray points along +z from the origin
+    let shear_x = direction[kx] / direction[kz];
+    let shear_y = direction[ky] / direction[kz];
+    let shear_z = 1.0 / direction[kz];
+
+    let project = |p: Vec3| {
+        let p = p - ray.origin;
+        let p = [p.x, p.y, p.z];
+        (p[kx] - shear_x * p[kz], p[ky] - shear_y * p[kz], shear_z * p[kz])
+    };
+    let (a_x, a_y, a_z) = project(a);
+    let (b_x, b_y, b_z) = project(b);
+    let (c_x, c_y, c_z) = project(c);
+
+    // scaled barycentrics of a, b and c, as edge functions around the ray
+    let e_a = c_x * b_y - c_y * b_x;
+    let e_b = a_x * c_y - a_y * c_x;
+    let e_c = b_x * a_y - b_y * a_x;
+
+    // inside when all share a sign, which depends on the side the ray comes from
+    let has_negative = e_a < 0.0 || e_b < 0.0 || e_c < 0.0;
+    let has_positive = e_a > 0.0 || e_b > 0.0 || e_c > 0.0;
+    if has_negative && has_positive { return None; }
+
+    let det = e_a + e_b + e_c;
+    if det == 0.0 { return None; }
+
+    let t = (e_a * a_z + e_b * b_z + e_c * c_z) / det;
+    if !ray_t.surrounds(t) { return None; }
+
+    Some((t, e_c / det, e_b / det))
+}
+
+const EPSILON: f64 = 1e-8;
+
+// Tangents along u and v from the texture coordinates at the corners, None for degenerate mappings
+pub(crate) fn uv_tangents([a, b, c]: [Vec3; 3], [uv_a, uv_b, uv_c]: [(f64, f64); 3]) -> Option<(Vec3, Vec3)> {
+    // solve edge = du * dpdu + dv * dpdv for both edges
+    let (du_1, dv_1) = (uv_b.0 - uv_a.0, uv_b.1 - uv_a.1);
+    let (du_2, dv_2) = (uv_c.0 - uv_a.0, uv_c.1 - uv_a.1);
+    let det = du_1 * dv_2 - dv_1 * du_2;
+    if det.abs() <= EPSILON { return None; }
+
+    let (edge_b, edge_c) = (b - a, c - a);
+    Some((
+        (dv_2 * edge_b - dv_1 * edge_c) / det,
+        (du_1 * edge_c - du_2 * edge_b) / det
+    ))
+}
+
+// Everything about a triangle needed to turn an intersection into a hit record,
+// shared by Triangle and the faces of a TriangleMesh
+pub(crate) struct Surface<'a> {
+    pub normal: Vec3,
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    pub uvs: Option<[(f64, f64); 3]>,
+    pub normals: Option<[Vec3; 3]>,
+    // glTF style, the direction of increasing u and the handedness of v.
+    // Replace dpdu and dpdv where there are vertex normals.
+    pub tangents: Option<[(Vec3, f64); 3]>,
+    pub colors: Option<[Vec3; 3]>,
+    pub material: &'a Arc<dyn Material>,
+    pub alpha_mask: Option<&'a AlphaMask>
+}
+
+// Interpolates the corner attributes at the barycentrics returned by intersect,
+// None where the alpha mask lets the ray through
+pub(crate) fn shade(surface: Surface, ray: Ray, t: f64, u: f64, v: f64) -> Option<HitRecord> {
+    let pos = ray.at(t);
+
+    // barycentric weights of a, b and c
+    let w = 1.0 - u - v;
+    let interpolate = |[value_a, value_b, value_c]: [Vec3; 3]| w * value_a + v * value_b + u * value_c;
+
+    let shading_normal = surface.normals.map(interpolate);
+    let vertex_color = surface.colors.map(interpolate);
 
-        let p_vec = ray.direction.cross(edge_2);
-        let det = edge_1.dot(p_vec);
-        if det < Self::EPSILON { return None; }
+    let (mut dpdu, mut dpdv) = (surface.dpdu, surface.dpdv);
+    if let (Some(tangents), Some(normal)) = (surface.tangents, shading_normal) {
+        dpdu = interpolate(tangents.map(|tangent| tangent.0));
+        dpdv = tangents[0].1.signum() * normal.cross(dpdu);
+    }
+
+    let (u, v) = match surface.uvs {
+        Some([uv_a, uv_b, uv_c]) => (
+            w * uv_a.0 + v * uv_b.0 + u * uv_c.0,
+            w * uv_a.1 + v * uv_b.1 + u * uv_c.1
+        ),
+        None => (u, v)
+    };
 
-        let t_vec = ray.origin - self.a;
-        let mut u = t_vec.dot(p_vec);
-        if u < 0.0 || u > det { return None; }
+    if let Some(alpha_mask) = surface.alpha_mask {
+        if alpha_mask.is_transparent(u, v, pos, surface.normal) { return None; }
+    }
 
-        let q_vec = t_vec.cross(edge_1);
-        let mut v = ray.direction.dot(q_vec);
-        if v < 0.0 || u + v > det { return None; }
+    let mut hit_record = HitRecord::new(
+        ray,
+        t,
+        pos,
+        surface.normal,
+        surface.material.clone(),
+        u,
+        v
+    ).with_tangents(dpdu, dpdv);
 
-        let mut t = edge_2.dot(q_vec);
-        let inv_det = 1.0 / det;
+    if let Some(vertex_color) = vertex_color {
+        hit_record = hit_record.with_vertex_color(vertex_color);
+    }
 
-        t *= inv_det;
-        u *= inv_det;
-        v *= inv_det;
+    match shading_normal {
+        Some(normal) => Some(hit_record.with_shading_normal(normal)),
+        None => Some(hit_record)
+    }
+}
 
-        let pos = ray.at(t);
+impl Hittable for Triangle {
+    fn hit(&self, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
+        let (t, u, v) = intersect([self.a, self.b, self.c], ray, ray_t, self.culling)?;
 
-        Some(HitRecord::new(
-            ray,
-            t,
-            pos,
-            self.normal,
-            self.material.clone(),
-            u,
-            v
-        ))
+        let surface = Surface {
+            normal: self.normal,
+            dpdu: self.dpdu,
+            dpdv: self.dpdv,
+            uvs: self.uvs,
+            normals: self.normals,
+            tangents: None,
+            colors: None,
+            material: &self.material,
+            alpha_mask: self.alpha_mask.as_ref()
+        };
+        shade(surface, ray, t, u, v)
     }
 
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: Interval = Interval { min: 0.0, max: f64::INFINITY };
+
+    // counter-clockwise seen from +z, so the normal points at +z
+    const TRIANGLE: [Vec3; 3] = [
+        Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+        Vec3 { x: 1.0, y: 0.0, z: 0.0 },
+        Vec3 { x: 0.0, y: 1.0, z: 0.0 }
+    ];
+
+    #[test]
+    fn barycentrics_weight_c_and_b() {
+        let ray = Ray::new(Vec3::new(0.25, 0.5, 2.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let (t, u, v) = intersect(TRIANGLE, ray, ALL, Culling::NONE).unwrap();
+
+        assert!((t - 2.0).abs() < 1e-12);
+        assert!((u - 0.5).abs() < 1e-12);
+        assert!((v - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn misses_outside_and_beyond_interval() {
+        let down = Vec3::new(0.0, 0.0, -1.0);
+        assert!(intersect(TRIANGLE, Ray::new(Vec3::new(0.6, 0.6, 1.0), down, 0.0), ALL, Culling::NONE).is_none());
+
+        let ray = Ray::new(Vec3::new(0.2, 0.2, 1.0), down, 0.0);
+        assert!(intersect(TRIANGLE, ray, Interval::new(0.0, 0.5), Culling::NONE).is_none());
+        assert!(intersect(TRIANGLE, ray, Interval::new(1.5, 3.0), Culling::NONE).is_none());
+    }
+
+    #[test]
+    fn culling_picks_a_side() {
+        let from_front = Ray::new(Vec3::new(0.2, 0.2, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let from_back = Ray::new(Vec3::new(0.2, 0.2, -1.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+
+        assert!(intersect(TRIANGLE, from_front, ALL, Culling::NONE).is_some());
+        assert!(intersect(TRIANGLE, from_back, ALL, Culling::NONE).is_some());
+        assert!(intersect(TRIANGLE, from_front, ALL, Culling::BACK).is_some());
+        assert!(intersect(TRIANGLE, from_back, ALL, Culling::BACK).is_none());
+        assert!(intersect(TRIANGLE, from_front, ALL, Culling::FRONT).is_none());
+        assert!(intersect(TRIANGLE, from_back, ALL, Culling::FRONT).is_some());
+    }
+
+    #[test]
+    fn shared_edges_are_watertight() {
+        // a skewed quad split along the diagonal from a to c
+        let a = Vec3::new(0.1, -0.3, 0.2);
+        let b = Vec3::new(1.7, 0.1, -0.4);
+        let c = Vec3::new(1.3, 1.9, 0.3);
+        let d = Vec3::new(-0.2, 1.1, 0.5);
+        let origins = [Vec3::new(0.3, 0.2, 5.0), Vec3::new(-3.1, 2.7, -4.3), Vec3::new(7.0, -1.0, 0.9)];
+
+        for origin in origins {
+            for step in 0..=1000 {
+                // points along the shared edge, vertices included
+                let target = a + (step as f64 / 1000.0) * (c - a);
+                let ray = Ray::new(origin, target - origin, 0.0);
+
+                let first = intersect([a, b, c], ray, ALL, Culling::NONE);
+                let second = intersect([a, c, d], ray, ALL, Culling::NONE);
+                assert!(first.is_some() || second.is_some(), "ray through {:?} slipped through", target);
+            }
+        }
+    }
+
+    #[test]
+    fn uv_tangents_follow_mapping() {
+        let (dpdu, dpdv) = uv_tangents(TRIANGLE, [(0.0, 0.0), (2.0, 0.0), (0.0, 4.0)]).unwrap();
+        assert!((dpdu - Vec3::new(0.5, 0.0, 0.0)).length() < 1e-12);
+        assert!((dpdv - Vec3::new(0.0, 0.25, 0.0)).length() < 1e-12);
+
+        assert!(uv_tangents(TRIANGLE, [(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)]).is_none());
+    }
+}
@@ -2,8 +2,11 @@ use std::rc::Rc;
 use std::sync::Arc;
 use crate::AABB::AABB;
 use crate::hittable::hittable::{HitRecord, Hittable};
+use crate::hittable::quad::Quad;
 use crate::material::{Isotropic, Material};
 use crate::ray::Ray;
+use crate::texture::texture::Texture;
+use crate::texture::voxel::{VoxelGrid, VoxelTexture};
 use crate::util::interval::Interval;
 use crate::util::vec3::Vec3;
 
@@ -65,4 +68,151 @@ impl Hittable for ConstantMedium {
 	fn bounding_box(&self) -> AABB {
 		self.boundary.bounding_box()
 	}
-}
\ No newline at end of file
+}
+
+// Medium with a density varying in space, e.g. clouds, smoke plumes and fire.
+// Free paths are sampled by delta tracking against a constant majorant, which must bound the density
+// everywhere inside the boundary. The boundary may be non-convex, every pair of crossings is a segment.
+pub struct HeterogeneousMedium {
+	boundary: Box<dyn Hittable>,
+	// channel average is the extinction coefficient
+	density: Arc<dyn Texture>,
+	majorant: f64,
+	phase_function: Arc<dyn Material>
+}
+
+impl HeterogeneousMedium {
+	// Gap between a boundary crossing and the search for the next one
+	const EPSILON: f64 = 0.0001;
+
+	pub fn new(boundary: Box<dyn Hittable>, density: Arc<dyn Texture>, majorant: f64, phase_function: Arc<dyn Material>) -> Self {
+		HeterogeneousMedium { boundary, density, majorant, phase_function }
+	}
+
+	// Fills the bounds of the grid, using its largest value as the majorant
+	pub fn from_grid(grid: Arc<VoxelGrid>, density_scale: f64, phase_function: Arc<dyn Material>) -> Self {
+		let bounds = grid.bounds();
+		let density = VoxelTexture::with_scale(grid, density_scale);
+		let majorant = density.max_scalar();
+
+		let boundary = Box::new(Quad::cube(
+			Vec3::new(bounds.x.min, bounds.y.min, bounds.z.min),
+			Vec3::new(bounds.x.max, bounds.y.max, bounds.z.max),
+			phase_function.clone()
+		));
+
+		HeterogeneousMedium { boundary, density: Arc::new(density), majorant, phase_function }
+	}
+
+	fn density_at(&self, p: Vec3) -> f64 {
+		let value = self.density.value_at(0.0, 0.0, p, Vec3::ZERO);
+		f64::max(0.0, (value.x + value.y + value.z) / 3.0)
+	}
+
+	// Next stretch of the ray inside the boundary starting at or after t_min
+	fn next_segment(&self, ray: Ray, t_min: f64) -> Option<(f64, f64)> {
+		let hit_record = self.boundary.hit(ray, Interval::new(t_min, f64::MAX))?;
+
+		// leaving through a back face means the ray started inside
+		if !hit_record.is_front_face { return Some((t_min, hit_record.t)); }
+
+		let exit = self.boundary.hit(ray, Interval::new(hit_record.t + Self::EPSILON, f64::MAX))?;
+		Some((hit_record.t, exit.t))
+	}
+
+	// Visits the segments overlapping ray_t until the callback returns a value
+	fn find_in_segments<T>(&self, ray: Ray, ray_t: Interval, mut f: impl FnMut(f64, f64) -> Option<T>) -> Option<T> {
+		let mut t = ray_t.min;
+
+		while let Some((entry, exit)) = self.next_segment(ray, t) {
+			if entry >= ray_t.max { return None; }
+
+			if let Some(result) = f(f64::max(entry, ray_t.min), f64::min(exit, ray_t.max)) {
+				return Some(result);
+			}
+
+			t = exit + Self::EPSILON;
+		}
+
+		None
+	}
+}
+
+impl Hittable for HeterogeneousMedium {
+	fn hit(&self, ray: Ray, ray_t: Interval) -> Option<HitRecord> {
+		if self.majorant <= 0.0 { return None; }
+
+		let ray_len = ray.direction.length();
+
+		// delta tracking: tentative collisions with the majorant, accepted with probability density / majorant
+		let t = self.find_in_segments(ray, ray_t, |start, end| {
+			let mut t = start;
+			loop {
+				t -= f64::ln(1.0 - fastrand::f64()) / (self.majorant * ray_len);
+				if t >= end { return None; }
+
+				if fastrand::f64() * self.majorant < self.density_at(ray.at(t)) { return Some(t); }
+			}
+		})?;
+
+		Some(HitRecord::new(
+			ray,
+			t,
+			ray.at(t),
+			Vec3::new(1.0, 0.0, 0.0),
+			self.phase_function.clone(),
+			0.0,
+			0.0
+		))
+	}
+
+	fn bounding_box(&self) -> AABB {
+		self.boundary.bounding_box()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hittable::hittable::HittableList;
+
+	// Density rising linearly with x, from 0 at x = 0 to 2 at x = 1
+	struct Gradient;
+
+	impl Texture for Gradient {
+		fn value_at(&self, _u: f64, _v: f64, p: Vec3, _normal: Vec3) -> Vec3 {
+			let density = 2.0 * p.x.clamp(0.0, 1.0);
+			Vec3::new(density, density, density)
+		}
+	}
+
+	fn unit_cube(offset: f64) -> HittableList {
+		let material: Arc<dyn Material> = Arc::new(Isotropic::from_color(Vec3::new(1.0, 1.0, 1.0)));
+		Quad::cube(Vec3::new(offset, 0.0, 0.0), Vec3::new(offset + 1.0, 1.0, 1.0), material)
+	}
+
+	// Fraction of rays along +x crossing the medium without a collision
+	fn transmitted_fraction(medium: &HeterogeneousMedium) -> f64 {
+		const RAYS: usize = 20000;
+		let ray = Ray::new(Vec3::new(-1.0, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0), 0.0);
+		let passed = (0..RAYS).filter(|_| medium.hit(ray, Interval::new(0.001, f64::INFINITY)).is_none()).count();
+		passed as f64 / RAYS as f64
+	}
+
+	#[test]
+	fn transmittance_matches_optical_depth() {
+		fastrand::seed(7);
+		let phase_function: Arc<dyn Material> = Arc::new(Isotropic::from_color(Vec3::new(1.0, 1.0, 1.0)));
+
+		// the optical depth through the cube is the integral of 2x over [0, 1]
+		let medium = HeterogeneousMedium::new(Box::new(unit_cube(0.0)), Arc::new(Gradient), 2.0, phase_function.clone());
+		assert!((transmitted_fraction(&medium) - f64::exp(-1.0)).abs() < 0.015);
+
+		// a second cube past the gradient is filled at the clamped density 2 and adds its own depth
+		let mut boundary = HittableList::new();
+		boundary.add(Box::new(unit_cube(0.0)));
+		boundary.add(Box::new(unit_cube(2.0)));
+		let medium = HeterogeneousMedium::new(Box::new(boundary), Arc::new(Gradient), 2.0, phase_function);
+		assert!((transmitted_fraction(&medium) - f64::exp(-3.0)).abs() < 0.01);
+	}
+}
@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use ::gltf::camera::Projection;
+use ::gltf::image::Format;
+use ::gltf::material::AlphaMode as GltfAlphaMode;
+use ::gltf::mesh::Mode;
+use ::gltf::texture::WrappingMode;
+use ::gltf::{buffer, image as gltf_image, Document, Node, Primitive};
+use image::{DynamicImage, ImageBuffer, Luma, LumaA, Rgb, Rgba};
+use log::warn;
+use crate::camera::{Background, Camera, SampleSettings};
+use crate::hittable::alpha::AlphaMask;
+use crate::hittable::hittable::HittableList;
+use crate::hittable::mesh::{MeshSettings, TriangleMesh};
+use crate::hittable::triangle::Culling;
+use crate::loader::{LoadError, MeshData};
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal, NormalMapped};
+use crate::texture::composite::MultiplyTexture;
+use crate::texture::image_texture::{ColorSpace, ImageAlphaTexture, ImageSettings, ImageTexture, WrapMode};
+use crate::texture::texture::{SolidColorTexture, Texture};
+use crate::util::transform::{Matrix4, Transform};
+use crate::util::vec3::Vec3;
+
+// Meshes of the default scene baked into world space, one per primitive, plus its first perspective camera
+pub struct GltfScene {
+	pub objects: HittableList,
+	pub camera: Option<GltfCamera>
+}
+
+// Everything a Camera needs that the file specifies
+pub struct GltfCamera {
+	// None leaves the aspect ratio to the caller
+	pub aspect_ratio: Option<f64>,
+	// degrees
+	pub v_fov: f64,
+	pub look_from: Vec3,
+	pub look_at: Vec3,
+	pub vup: Vec3
+}
+
+impl GltfCamera {
+	pub fn to_camera(
+		&self,
+		default_aspect_ratio: f64,
+		image_width: i32,
+		sample_settings: SampleSettings,
+		max_depth: u32,
+		background: Background
+	) -> Camera {
+		Camera::new(
+			self.aspect_ratio.unwrap_or(default_aspect_ratio),
+			image_width,
+			sample_settings,
+			max_depth,
+			self.v_fov,
+			self.look_from,
+			self.look_at,
+			self.vup,
+			0.0,
+			(self.look_at - self.look_from).length(),
+			background
+		)
+	}
+}
+
+// glTF 2.0 import from a .gltf with external or embedded buffers, or a .glb.
+// Primitives without a material get the default one.
+pub fn load<P: AsRef<Path>>(filepath: P, default_material: Arc<dyn Material>) -> Result<GltfScene, LoadError> {
+	let (document, buffers, images) = ::gltf::import(filepath)?;
+
+	let mut importer = Importer {
+		buffers,
+		images,
+		textures: HashMap::new(),
+		materials: HashMap::new(),
+		default_material,
+		objects: HittableList::new(),
+		camera: None
+	};
+
+	let scene = document.default_scene().or_else(|| document.scenes().next());
+	match scene {
+		Some(scene) => {
+			for node in scene.nodes() {
+				importer.visit(node, &Transform::IDENTITY)?;
+			}
+		}
+		None => warn!("glTF file has no scene, nothing imported"),
+	}
+
+	warn_unused_cameras(&document, importer.camera.is_some());
+
+	Ok(GltfScene { objects: importer.objects, camera: importer.camera })
+}
+
+fn warn_unused_cameras(document: &Document, found: bool) {
+	if !found && document.cameras().len() > 0 {
+		warn!("glTF file has cameras, but none is perspective and placed in the scene");
+	}
+}
+
+struct Importer {
+	buffers: Vec<buffer::Data>,
+	images: Vec<gltf_image::Data>,
+	// keyed by glTF texture index, so every sampler gets its own settings
+	textures: HashMap<(usize, ColorSpace), Arc<ImageTexture>>,
+	// keyed by glTF material index
+	materials: HashMap<usize, ImportedMaterial>,
+	default_material: Arc<dyn Material>,
+	objects: HittableList,
+	camera: Option<GltfCamera>
+}
+
+#[derive(Clone)]
+struct ImportedMaterial {
+	material: Arc<dyn Material>,
+	alpha_mask: Option<(Arc<dyn Texture>, GltfAlphaMode, f64)>,
+	culling: Culling
+}
+
+impl Importer {
+	fn visit(&mut self, node: Node, parent: &Transform) -> Result<(), LoadError> {
+		let local = node.transform().matrix().map(|column| column.map(|value| value as f64));
+		// a zero scale hides the node and everything below it
+		let Some(transform) = Transform::from_matrix(*parent.matrix() * Matrix4::from_columns(local)) else {
+			return Ok(());
+		};
+
+		if let Some(mesh) = node.mesh() {
+			for primitive in mesh.primitives() {
+				self.add_primitive(&primitive, &transform)?;
+			}
+		}
+
+		if let (Some(camera), None) = (node.camera(), &self.camera) {
+			match camera.projection() {
+				Projection::Perspective(perspective) => {
+					// cameras look down -z with +y up
+					self.camera = Some(GltfCamera {
+						aspect_ratio: perspective.aspect_ratio().map(|aspect| aspect as f64),
+						v_fov: (perspective.yfov() as f64).to_degrees(),
+						look_from: transform.point(Vec3::ZERO),
+						look_at: transform.point(Vec3::new(0.0, 0.0, -1.0)),
+						vup: transform.vector(Vec3::new(0.0, 1.0, 0.0))
+					});
+				}
+				Projection::Orthographic(_) => warn!("skipping orthographic glTF camera, only perspective is supported"),
+			}
+		}
+
+		for child in node.children() {
+			self.visit(child, &transform)?;
+		}
+
+		Ok(())
+	}
+
+	fn add_primitive(&mut self, primitive: &Primitive, transform: &Transform) -> Result<(), LoadError> {
+		if primitive.mode() != Mode::Triangles {
+			warn!("skipping glTF primitive with mode {:?}, only triangle lists are supported", primitive.mode());
+			return Ok(());
+		}
+
+		let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+
+		let Some(positions) = reader.read_positions() else {
+			return Err(LoadError::invalid("glTF primitive without positions"));
+		};
+		let positions: Vec<Vec3> = positions.map(|p| transform.point(to_vec3(p))).collect();
+
+		let normals: Option<Vec<Vec3>> = reader.read_normals()
+			.map(|normals| normals.map(|n| transform.normal(to_vec3(n)).unit()).collect());
+
+		let tangents: Option<Vec<(Vec3, f64)>> = reader.read_tangents()
+			.map(|tangents| tangents.map(|[x, y, z, w]| {
+				(transform.vector(to_vec3([x, y, z])), w as f64)
+			}).collect());
+
+		// glTF puts the origin of texture space at the top left of the image
+		let uvs: Option<Vec<(f64, f64)>> = reader.read_tex_coords(0)
+			.map(|uvs| uvs.into_f32().map(|[u, v]| (u as f64, 1.0 - v as f64)).collect());
+
+		let indices: Vec<usize> = match reader.read_indices() {
+			Some(indices) => indices.into_u32().map(|index| index as usize).collect(),
+			None => (0..positions.len()).collect()
+		};
+
+		// a mirroring transform turns the winding around
+		let mirrored = transform.is_mirroring();
+		let faces = indices.chunks_exact(3)
+			.map(|face| if mirrored { [face[0], face[2], face[1]] } else { [face[0], face[1], face[2]] })
+			.collect();
+
+		// tangent space needs the shading normals of the face
+		let tangents = tangents.filter(|_| normals.is_some());
+		let mesh = MeshData { positions, normals, uvs, tangents, colors: None, faces };
+		mesh.validate()?;
+
+		let imported = match primitive.material().index() {
+			Some(index) => self.material(index, &primitive.material())?,
+			None => ImportedMaterial { material: self.default_material.clone(), alpha_mask: None, culling: Culling::NONE }
+		};
+
+		let alpha_mask = match &imported.alpha_mask {
+			Some((texture, GltfAlphaMode::Mask, cutoff)) => Some(AlphaMask::threshold(texture.clone(), *cutoff)),
+			Some((texture, _, _)) => Some(AlphaMask::stochastic(texture.clone())),
+			None => None
+		};
+
+		let settings = MeshSettings { alpha_mask, culling: imported.culling, ..MeshSettings::default() };
+		self.objects.add(Box::new(TriangleMesh::with_settings(mesh.into_buffers(), imported.material, settings)));
+
+		Ok(())
+	}
+
+	// Picks the closest of our materials: emitters, then transmissive glass, then metals, otherwise diffuse.
+	// Metalness and roughness are taken from the factors, a per-texel mix has no counterpart.
+	fn material(&mut self, index: usize, material: &::gltf::Material) -> Result<ImportedMaterial, LoadError> {
+		if let Some(imported) = self.materials.get(&index) {
+			return Ok(imported.clone());
+		}
+
+		let pbr = material.pbr_metallic_roughness();
+		let [r, g, b, alpha] = pbr.base_color_factor();
+		let base_color = Vec3::new(r as f64, g as f64, b as f64);
+
+		let base_texture = match pbr.base_color_texture() {
+			Some(info) => Some(self.texture(info.texture(), info.tex_coord(), ColorSpace::SRGB)?),
+			None => None
+		};
+		let base_color_texture = base_texture.as_ref().map(|texture| scaled(texture.clone(), base_color));
+
+		let emission = to_vec3(material.emissive_factor()) * material.emissive_strength().unwrap_or(1.0) as f64;
+		let emissive_texture = match material.emissive_texture() {
+			Some(info) => Some(self.texture(info.texture(), info.tex_coord(), ColorSpace::SRGB)?),
+			None => None
+		};
+
+		let transmission = material.transmission().map_or(0.0, |transmission| transmission.transmission_factor());
+
+		let mut result: Arc<dyn Material> = if !emission.is_near_zero() {
+			match emissive_texture {
+				Some(texture) => Arc::new(DiffuseLight::new(scaled(texture, emission))),
+				None => Arc::new(DiffuseLight::from_color(emission))
+			}
+		} else if transmission >= 0.5 {
+			Arc::new(Dielectric::new(material.ior().unwrap_or(1.5) as f64))
+		} else if pbr.metallic_factor() >= 0.5 {
+			Arc::new(Metal::new(base_color, pbr.roughness_factor() as f64))
+		} else {
+			match base_color_texture {
+				Some(texture) => Arc::new(Lambertian::from_texture(texture)),
+				None => Arc::new(Lambertian::from_color(base_color))
+			}
+		};
+
+		if let Some(info) = material.normal_texture() {
+			let normal_map = self.texture(info.texture(), info.tex_coord(), ColorSpace::LINEAR)?;
+			result = Arc::new(NormalMapped::new(result, normal_map, info.scale() as f64));
+		}
+
+		// opacity is the base alpha, the texture's times the factor
+		let alpha_mode = material.alpha_mode();
+		let alpha_mask = match (alpha_mode, base_texture) {
+			(GltfAlphaMode::Opaque, _) => None,
+			(_, Some(texture)) => {
+				let opacity = scaled(Arc::new(ImageAlphaTexture::new(texture)), Vec3::new(alpha as f64, alpha as f64, alpha as f64));
+				Some((opacity, alpha_mode, material.alpha_cutoff().unwrap_or(0.5) as f64))
+			}
+			(_, None) if alpha < 1.0 => {
+				let opacity: Arc<dyn Texture> = Arc::new(SolidColorTexture::new(Vec3::new(alpha as f64, alpha as f64, alpha as f64)));
+				Some((opacity, alpha_mode, material.alpha_cutoff().unwrap_or(0.5) as f64))
+			}
+			(_, None) => None
+		};
+
+		// single sided materials are culled from behind, except glass which rays must also leave
+		let culling = if material.double_sided() || transmission >= 0.5 { Culling::NONE } else { Culling::BACK };
+
+		let imported = ImportedMaterial { material: result, alpha_mask, culling };
+		self.materials.insert(index, imported.clone());
+
+		Ok(imported)
+	}
+
+	fn texture(&mut self, texture: ::gltf::Texture, tex_coord: u32, color_space: ColorSpace) -> Result<Arc<ImageTexture>, LoadError> {
+		if tex_coord != 0 {
+			warn!("glTF texture {} uses texture coordinate set {}, only set 0 is imported", texture.index(), tex_coord);
+		}
+
+		let key = (texture.index(), color_space);
+		if let Some(image) = self.textures.get(&key) {
+			return Ok(image.clone());
+		}
+
+		// ImageSettings has one wrap mode for both axes
+		let wrap_mode = match texture.sampler().wrap_s() {
+			WrappingMode::ClampToEdge => WrapMode::CLAMP,
+			WrappingMode::MirroredRepeat => WrapMode::MIRROR,
+			WrappingMode::Repeat => WrapMode::REPEAT
+		};
+		let settings = ImageSettings { color_space, wrap_mode, ..ImageSettings::default() };
+
+		let data = &self.images[texture.source().index()];
+		let image = Arc::new(ImageTexture::from_image(to_dynamic_image(data)?, settings));
+		self.textures.insert(key, image.clone());
+
+		Ok(image)
+	}
+}
+
+fn scaled(texture: Arc<dyn Texture>, factor: Vec3) -> Arc<dyn Texture> {
+	if factor.x == 1.0 && factor.y == 1.0 && factor.z == 1.0 {
+		return texture;
+	}
+	Arc::new(MultiplyTexture::new(texture, Arc::new(SolidColorTexture::new(factor))))
+}
+
+fn to_vec3([x, y, z]: [f32; 3]) -> Vec3 {
+	Vec3::new(x as f64, y as f64, z as f64)
+}
+
+// The importer hands out raw pixels in native byte order, wrap them back into an image
+fn to_dynamic_image(data: &gltf_image::Data) -> Result<DynamicImage, LoadError> {
+	let (width, height) = (data.width, data.height);
+	let invalid = || LoadError::invalid("glTF image data does not match its size");
+
+	let words = |bytes: &[u8]| -> Vec<u16> {
+		bytes.chunks_exact(2).map(|word| u16::from_ne_bytes([word[0], word[1]])).collect()
+	};
+	let floats = |bytes: &[u8]| -> Vec<f32> {
+		bytes.chunks_exact(4).map(|float| f32::from_ne_bytes([float[0], float[1], float[2], float[3]])).collect()
+	};
+
+	let pixels = data.pixels.clone();
+	let image = match data.format {
+		Format::R8 => ImageBuffer::<Luma<u8>, _>::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8),
+		Format::R8G8 => ImageBuffer::<LumaA<u8>, _>::from_raw(width, height, pixels).map(DynamicImage::ImageLumaA8),
+		Format::R8G8B8 => ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8),
+		Format::R8G8B8A8 => ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, pixels).map(DynamicImage::ImageRgba8),
+		Format::R16 => ImageBuffer::<Luma<u16>, _>::from_raw(width, height, words(&pixels)).map(DynamicImage::ImageLuma16),
+		Format::R16G16 => ImageBuffer::<LumaA<u16>, _>::from_raw(width, height, words(&pixels)).map(DynamicImage::ImageLumaA16),
+		Format::R16G16B16 => ImageBuffer::<Rgb<u16>, _>::from_raw(width, height, words(&pixels)).map(DynamicImage::ImageRgb16),
+		Format::R16G16B16A16 => ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, words(&pixels)).map(DynamicImage::ImageRgba16),
+		Format::R32G32B32FLOAT => ImageBuffer::<Rgb<f32>, _>::from_raw(width, height, floats(&pixels)).map(DynamicImage::ImageRgb32F),
+		Format::R32G32B32A32FLOAT => ImageBuffer::<Rgba<f32>, _>::from_raw(width, height, floats(&pixels)).map(DynamicImage::ImageRgba32F)
+	};
+
+	image.ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hittable::hittable::Hittable;
+	use crate::loader::test_file;
+	use crate::ray::Ray;
+	use crate::util::interval::Interval;
+
+	// Unit quad in the xy plane facing +z, scaled by 2 under a parent 3 units down -z,
+	// and a perspective camera 2 units up +z
+	fn scene(name: &str, material: &str) -> std::path::PathBuf {
+		let mut buffer = Vec::new();
+		for value in [-1.0f32, -1.0, 0.0, 1.0, -1.0, 0.0, 1.0, 1.0, 0.0, -1.0, 1.0, 0.0] {
+			buffer.extend(value.to_le_bytes());
+		}
+		for index in [0u16, 1, 2, 0, 2, 3] {
+			buffer.extend(index.to_le_bytes());
+		}
+		let buffer_path = test_file(&format!("{}.bin", name), &buffer);
+
+		let source = format!(r#"{{
+			"asset": {{ "version": "2.0" }},
+			"scene": 0,
+			"scenes": [{{ "nodes": [0, 2] }}],
+			"nodes": [
+				{{ "children": [1], "translation": [0, 0, -3] }},
+				{{ "mesh": 0, "scale": [2, 2, 2] }},
+				{{ "camera": 0, "translation": [0, 0, 2] }}
+			],
+			"cameras": [{{ "type": "perspective", "perspective": {{ "yfov": 0.5, "aspectRatio": 1.5, "znear": 0.1 }} }}],
+			"meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1 {} }}] }}],
+			"materials": [{{ "pbrMetallicRoughness": {{ "baseColorFactor": [0.9, 0.6, 0.2, 1], "metallicFactor": 0 }} }}],
+			"buffers": [{{ "byteLength": 60, "uri": "{}" }}],
+			"bufferViews": [
+				{{ "buffer": 0, "byteOffset": 0, "byteLength": 48 }},
+				{{ "buffer": 0, "byteOffset": 48, "byteLength": 12 }}
+			],
+			"accessors": [
+				{{ "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3", "min": [-1, -1, 0], "max": [1, 1, 0] }},
+				{{ "bufferView": 1, "componentType": 5123, "count": 6, "type": "SCALAR" }}
+			]
+		}}"#, material, buffer_path.file_name().unwrap().to_str().unwrap());
+
+		test_file(&format!("{}.gltf", name), source.as_bytes())
+	}
+
+	fn gray() -> Arc<dyn Material> {
+		Arc::new(Lambertian::from_color(Vec3::new(0.5, 0.5, 0.5)))
+	}
+
+	#[test]
+	fn nodes_are_baked_into_world_space() {
+		let scene = load(scene("world", ""), gray()).unwrap();
+		let ray_t = Interval::new(0.001, f64::INFINITY);
+		let down = Vec3::new(0.0, 0.0, -1.0);
+
+		let hit = scene.objects.hit(Ray::new(Vec3::new(1.5, -1.5, 0.0), down, 0.0), ray_t).unwrap();
+		assert!((hit.t - 3.0).abs() < 1e-9);
+		assert!(scene.objects.hit(Ray::new(Vec3::new(2.5, 0.0, 0.0), down, 0.0), ray_t).is_none());
+	}
+
+	#[test]
+	fn perspective_camera() {
+		let camera = load(scene("camera", ""), gray()).unwrap().camera.unwrap();
+
+		assert_eq!(camera.aspect_ratio, Some(1.5));
+		assert!((camera.v_fov - 0.5f64.to_degrees()).abs() < 1e-4);
+		assert!((camera.look_from - Vec3::new(0.0, 0.0, 2.0)).length() < 1e-9);
+		assert!((camera.look_at - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+		assert!((camera.vup - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-9);
+	}
+
+	#[test]
+	fn single_sided_materials_cull_back_faces() {
+		let ray_t = Interval::new(0.001, f64::INFINITY);
+		let from_back = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+
+		// without a material the default one is hit from both sides
+		assert!(load(scene("default", ""), gray()).unwrap().objects.hit(from_back, ray_t).is_some());
+		assert!(load(scene("single", r#", "material": 0"#), gray()).unwrap().objects.hit(from_back, ray_t).is_none());
+	}
+
+	#[test]
+	fn invalid_files() {
+		assert!(matches!(load(test_file("broken.gltf", b"{ \"asset\": "), gray()), Err(LoadError::GLTF(_))));
+
+		// an index past the four vertices
+		let path = scene("indices", "");
+		let buffer_path = path.with_extension("bin");
+		let mut buffer = std::fs::read(&buffer_path).unwrap();
+		buffer[58] = 4;
+		std::fs::write(&buffer_path, buffer).unwrap();
+		assert!(load(path, gray()).is_err());
+	}
+}
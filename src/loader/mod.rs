@@ -0,0 +1,124 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use image::ImageError;
+use crate::hittable::mesh::{MeshBuffers, TriangleMesh};
+use crate::material::Material;
+use crate::util::vec3::Vec3;
+
+pub mod gltf;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+// Failure while importing a scene file
+#[derive(Debug)]
+pub enum LoadError {
+	IO(std::io::Error),
+	IMAGE(ImageError),
+	GLTF(::gltf::Error),
+	// malformed content, line is 1-based when known
+	PARSE { line: Option<usize>, message: String }
+}
+
+impl LoadError {
+	pub fn parse(line: usize, message: impl Into<String>) -> Self {
+		LoadError::PARSE { line: Some(line), message: message.into() }
+	}
+
+	pub fn invalid(message: impl Into<String>) -> Self {
+		LoadError::PARSE { line: None, message: message.into() }
+	}
+}
+
+impl Display for LoadError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			LoadError::IO(error) => write!(f, "{}", error),
+			LoadError::IMAGE(error) => write!(f, "{}", error),
+			LoadError::GLTF(error) => write!(f, "{}", error),
+			LoadError::PARSE { line: Some(line), message } => write!(f, "line {}: {}", line, message),
+			LoadError::PARSE { line: None, message } => write!(f, "{}", message)
+		}
+	}
+}
+
+impl Error for LoadError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			LoadError::IO(error) => Some(error),
+			LoadError::IMAGE(error) => Some(error),
+			LoadError::GLTF(error) => Some(error),
+			LoadError::PARSE { .. } => None
+		}
+	}
+}
+
+impl From<std::io::Error> for LoadError {
+	fn from(error: std::io::Error) -> Self {
+		LoadError::IO(error)
+	}
+}
+
+impl From<ImageError> for LoadError {
+	fn from(error: ImageError) -> Self {
+		LoadError::IMAGE(error)
+	}
+}
+
+impl From<::gltf::Error> for LoadError {
+	fn from(error: ::gltf::Error) -> Self {
+		LoadError::GLTF(error)
+	}
+}
+
+// Indexed triangles with optional per-vertex attributes, as read from mesh formats
+// without materials. Faces wind counter-clockwise seen from the front.
+pub struct MeshData {
+	pub positions: Vec<Vec3>,
+	pub normals: Option<Vec<Vec3>>,
+	pub uvs: Option<Vec<(f64, f64)>>,
+	// glTF style: the direction of increasing u and the sign of normal x tangent along increasing v
+	pub tangents: Option<Vec<(Vec3, f64)>>,
+	// linear RGB
+	pub colors: Option<Vec<Vec3>>,
+	pub faces: Vec<[usize; 3]>
+}
+
+impl MeshData {
+	// Faces index into shared vertex buffers, the vertex colors show through a material
+	// textured with a VertexColorTexture
+	pub fn mesh(self, material: Arc<dyn Material>) -> TriangleMesh {
+		TriangleMesh::new(self.into_buffers(), material)
+	}
+
+	fn into_buffers(self) -> MeshBuffers {
+		MeshBuffers {
+			positions: self.positions,
+			normals: self.normals,
+			uvs: self.uvs,
+			tangents: self.tangents,
+			colors: self.colors,
+			faces: self.faces.into_iter().map(|face| face.map(|index| index as u32)).collect()
+		}
+	}
+
+	// Checks every face index against the vertex count
+	fn validate(&self) -> Result<(), LoadError> {
+		let count = self.positions.len();
+
+		if let Some(face) = self.faces.iter().find(|face| face.iter().any(|&index| index >= count)) {
+			return Err(LoadError::invalid(format!("face {:?} references a vertex beyond the {} in the mesh", face, count)));
+		}
+
+		Ok(())
+	}
+}
+
+// Writes `contents` to a file in the temp directory, unique to this process, for the parser tests
+#[cfg(test)]
+pub(crate) fn test_file(name: &str, contents: &[u8]) -> std::path::PathBuf {
+	let path = std::env::temp_dir().join(format!("grayshift-{}-{}", std::process::id(), name));
+	std::fs::write(&path, contents).unwrap();
+	path
+}
@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::hittable::hittable::HittableList;
+use crate::hittable::triangle::Triangle;
+use crate::loader::LoadError;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::texture::cache::TextureCache;
+use crate::util::vec3::Vec3;
+
+// Wavefront .obj import. Polygons are fan triangulated, materials come from the .mtl libraries
+// named by `mtllib` and faces before any `usemtl` get the default material.
+pub fn load<P: AsRef<Path>>(
+	filepath: P,
+	default_material: Arc<dyn Material>,
+	texture_cache: &mut TextureCache
+) -> Result<HittableList, LoadError> {
+	let filepath = filepath.as_ref();
+	let directory = filepath.parent().unwrap_or(Path::new(""));
+	let source = fs::read_to_string(filepath)?;
+
+	let mut positions: Vec<Vec3> = Vec::new();
+	let mut uvs: Vec<(f64, f64)> = Vec::new();
+	let mut normals: Vec<Vec3> = Vec::new();
+
+	let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+	let mut material = default_material.clone();
+
+	let mut triangles = HittableList::new();
+
+	for (index, line) in source.lines().enumerate() {
+		let line_number = index + 1;
+		let mut tokens = line.split_whitespace();
+
+		match tokens.next() {
+			Some("v") => positions.push(parse_vec3(&mut tokens, line_number)?),
+			Some("vt") => {
+				let u = parse_f64(tokens.next(), line_number)?;
+				// v is optional for 1D textures
+				let v = match tokens.next() {
+					Some(token) => parse_f64(Some(token), line_number)?,
+					None => 0.0
+				};
+				uvs.push((u, v));
+			}
+			Some("vn") => normals.push(parse_vec3(&mut tokens, line_number)?),
+			Some("f") => {
+				let corners = tokens
+					.map(|token| FaceVertex::parse(token, positions.len(), uvs.len(), normals.len(), line_number))
+					.collect::<Result<Vec<_>, _>>()?;
+
+				if corners.len() < 3 {
+					return Err(LoadError::parse(line_number, "face with fewer than 3 vertices"));
+				}
+
+				for i in 1..corners.len() - 1 {
+					let triangle = [corners[0], corners[i], corners[i + 1]];
+					triangles.add(Box::new(build_triangle(triangle, &positions, &uvs, &normals, material.clone())));
+				}
+			}
+			Some("mtllib") => {
+				// several libraries may be listed on one line
+				for name in tokens {
+					materials.extend(load_mtl(directory.join(name), texture_cache)?);
+				}
+			}
+			Some("usemtl") => {
+				let name = tokens.collect::<Vec<_>>().join(" ");
+				material = match materials.get(&name) {
+					Some(material) => material.clone(),
+					None => default_material.clone()
+				};
+			}
+			// groups, objects and smoothing groups do not change the geometry
+			_ => {}
+		}
+	}
+
+	Ok(triangles)
+}
+
+// Indices into the attribute arrays for one corner of a face
+#[derive(Copy, Clone)]
+struct FaceVertex {
+	position: usize,
+	uv: Option<usize>,
+	normal: Option<usize>
+}
+
+impl FaceVertex {
+	// `v`, `v/vt`, `v//vn` or `v/vt/vn`, indices are 1-based or negative relative to the end
+	fn parse(token: &str, positions: usize, uvs: usize, normals: usize, line: usize) -> Result<Self, LoadError> {
+		let mut parts = token.split('/');
+
+		let position = resolve_index(parts.next(), positions, line)?
+			.ok_or_else(|| LoadError::parse(line, "face vertex without a position"))?;
+		let uv = resolve_index(parts.next(), uvs, line)?;
+		let normal = resolve_index(parts.next(), normals, line)?;
+
+		Ok(FaceVertex { position, uv, normal })
+	}
+}
+
+fn resolve_index(token: Option<&str>, count: usize, line: usize) -> Result<Option<usize>, LoadError> {
+	let Some(token) = token.filter(|token| !token.is_empty()) else { return Ok(None); };
+
+	let index: i64 = token.parse()
+		.map_err(|_| LoadError::parse(line, format!("invalid index '{}'", token)))?;
+
+	let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+	if resolved < 0 || resolved >= count as i64 {
+		return Err(LoadError::parse(line, format!("index {} out of range", index)));
+	}
+
+	Ok(Some(resolved as usize))
+}
+
+fn build_triangle(
+	corners: [FaceVertex; 3],
+	positions: &[Vec3],
+	uvs: &[(f64, f64)],
+	normals: &[Vec3],
+	material: Arc<dyn Material>
+) -> Triangle {
+	let [a, b, c] = corners;
+
+	let triangle = Triangle::new(positions[a.position], positions[b.position], positions[c.position], material);
+
+	let triangle = match (a.uv, b.uv, c.uv) {
+		(Some(uv_a), Some(uv_b), Some(uv_c)) => triangle.with_uvs(uvs[uv_a], uvs[uv_b], uvs[uv_c]),
+		_ => triangle
+	};
+
+	// faces without normals on every corner stay flat shaded
+	match (a.normal, b.normal, c.normal) {
+		(Some(normal_a), Some(normal_b), Some(normal_c)) => triangle.with_normals(normals[normal_a], normals[normal_b], normals[normal_c]),
+		_ => triangle
+	}
+}
+
+// Material library, keyed by the names used in `usemtl`
+pub fn load_mtl<P: AsRef<Path>>(filepath: P, texture_cache: &mut TextureCache) -> Result<HashMap<String, Arc<dyn Material>>, LoadError> {
+	let filepath = filepath.as_ref();
+	let directory = filepath.parent().unwrap_or(Path::new(""));
+	let source = fs::read_to_string(filepath)?;
+
+	let mut definitions: Vec<(String, MtlDefinition)> = Vec::new();
+
+	for (index, line) in source.lines().enumerate() {
+		let line_number = index + 1;
+		let mut tokens = line.split_whitespace();
+		let Some(keyword) = tokens.next() else { continue; };
+
+		if keyword == "newmtl" {
+			definitions.push((tokens.collect::<Vec<_>>().join(" "), MtlDefinition::default()));
+			continue;
+		}
+
+		if keyword.starts_with('#') { continue; }
+
+		let Some((_, definition)) = definitions.last_mut() else {
+			return Err(LoadError::parse(line_number, format!("'{}' before any newmtl", keyword)));
+		};
+
+		match keyword {
+			"Kd" => definition.diffuse = parse_vec3(&mut tokens, line_number)?,
+			"Ks" => definition.specular = parse_vec3(&mut tokens, line_number)?,
+			"Ke" => definition.emission = parse_vec3(&mut tokens, line_number)?,
+			"Ns" => definition.shininess = parse_f64(tokens.next(), line_number)?,
+			"Ni" => definition.refraction_index = parse_f64(tokens.next(), line_number)?,
+			"d" => definition.dissolve = parse_f64(tokens.next(), line_number)?,
+			"Tr" => definition.dissolve = 1.0 - parse_f64(tokens.next(), line_number)?,
+			"illum" => definition.illumination = parse_f64(tokens.next(), line_number)? as u32,
+			// options such as `-s 1 1 1` come before the file name
+			"map_Kd" => definition.diffuse_map = tokens.last().map(|name| directory.join(name)),
+			"map_Ke" => definition.emission_map = tokens.last().map(|name| directory.join(name)),
+			_ => {}
+		}
+	}
+
+	let mut materials = HashMap::new();
+	for (name, definition) in definitions {
+		materials.insert(name, definition.to_material(texture_cache)?);
+	}
+
+	Ok(materials)
+}
+
+// Phong parameters of one `newmtl` block
+struct MtlDefinition {
+	diffuse: Vec3,
+	specular: Vec3,
+	emission: Vec3,
+	shininess: f64,
+	refraction_index: f64,
+	dissolve: f64,
+	illumination: u32,
+	diffuse_map: Option<PathBuf>,
+	emission_map: Option<PathBuf>
+}
+
+impl Default for MtlDefinition {
+	fn default() -> Self {
+		MtlDefinition {
+			diffuse: Vec3::new(0.8, 0.8, 0.8),
+			specular: Vec3::ZERO,
+			emission: Vec3::ZERO,
+			shininess: 0.0,
+			refraction_index: 1.5,
+			dissolve: 1.0,
+			illumination: 2,
+			diffuse_map: None,
+			emission_map: None
+		}
+	}
+}
+
+impl MtlDefinition {
+	// Picks the closest of our materials: emitters, then glass, then mirrors, otherwise diffuse
+	fn to_material(&self, texture_cache: &mut TextureCache) -> Result<Arc<dyn Material>, LoadError> {
+		if let Some(path) = &self.emission_map {
+			return Ok(Arc::new(DiffuseLight::new(texture_cache.load(path)?)));
+		}
+		if !self.emission.is_near_zero() {
+			return Ok(Arc::new(DiffuseLight::from_color(self.emission)));
+		}
+
+		// illumination models 4, 6, 7 and 9 are the refractive ones
+		if self.dissolve < 1.0 || matches!(self.illumination, 4 | 6 | 7 | 9) {
+			return Ok(Arc::new(Dielectric::new(self.refraction_index)));
+		}
+
+		let is_mirror = self.illumination == 3
+			|| (!self.specular.is_near_zero() && self.diffuse.is_near_zero() && self.diffuse_map.is_none());
+		if is_mirror {
+			// Phong exponent to the roughness of a matching Beckmann lobe
+			let fuzz = f64::sqrt(2.0 / (self.shininess + 2.0)).clamp(0.0, 1.0);
+			return Ok(Arc::new(Metal::new(self.specular, fuzz)));
+		}
+
+		match &self.diffuse_map {
+			Some(path) => Ok(Arc::new(Lambertian::from_texture(texture_cache.load(path)?))),
+			None => Ok(Arc::new(Lambertian::from_color(self.diffuse)))
+		}
+	}
+}
+
+fn parse_f64(token: Option<&str>, line: usize) -> Result<f64, LoadError> {
+	let token = token.ok_or_else(|| LoadError::parse(line, "missing number"))?;
+	token.parse().map_err(|_| LoadError::parse(line, format!("invalid number '{}'", token)))
+}
+
+fn parse_vec3<'a>(tokens: &mut impl Iterator<Item = &'a str>, line: usize) -> Result<Vec3, LoadError> {
+	Ok(Vec3::new(
+		parse_f64(tokens.next(), line)?,
+		parse_f64(tokens.next(), line)?,
+		parse_f64(tokens.next(), line)?
+	))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hittable::hittable::Hittable;
+	use crate::loader::test_file;
+	use crate::ray::Ray;
+	use crate::util::interval::Interval;
+
+	fn gray() -> Arc<dyn Material> {
+		Arc::new(Lambertian::from_color(Vec3::new(0.5, 0.5, 0.5)))
+	}
+
+	fn load_source(name: &str, source: &str) -> Result<HittableList, LoadError> {
+		load(test_file(name, source.as_bytes()), gray(), &mut TextureCache::new())
+	}
+
+	fn assert_parse_error(result: Result<HittableList, LoadError>, expected_line: usize) {
+		match result {
+			Err(LoadError::PARSE { line, .. }) => assert_eq!(line, Some(expected_line)),
+			Err(error) => panic!("unexpected error {}", error),
+			Ok(_) => panic!("loaded invalid file")
+		}
+	}
+
+	#[test]
+	fn quad_is_fan_triangulated() {
+		let source = "\
+# unit quad in the xy plane
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+g quad
+f 1/1/1 2/2/1 3/3/1 4/4/1
+";
+		let triangles = load_source("quad.obj", source).unwrap();
+		assert_eq!(triangles.objects.len(), 2);
+
+		let ray = Ray::new(Vec3::new(0.25, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+		let hit = triangles.hit(ray, Interval::new(0.001, f64::INFINITY)).unwrap();
+		assert!((hit.u - 0.25).abs() < 1e-12 && (hit.v - 0.75).abs() < 1e-12);
+	}
+
+	#[test]
+	fn relative_and_partial_indices() {
+		let source = "\
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vn 0 0 1
+f -3//-1 -2//-1 -1//-1
+f 1 2 3
+";
+		assert_eq!(load_source("relative.obj", source).unwrap().objects.len(), 2);
+	}
+
+	#[test]
+	fn malformed_faces_report_their_line() {
+		assert_parse_error(load_source("range.obj", "v 0 0 0\nv 1 0 0\nf 1 2 3\n"), 3);
+		assert_parse_error(load_source("short.obj", "v 0 0 0\nv 1 0 0\n\nf 1 2\n"), 4);
+		assert_parse_error(load_source("zero.obj", "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 0 1 2\n"), 4);
+		assert_parse_error(load_source("number.obj", "v 0 zero 0\n"), 1);
+	}
+
+	#[test]
+	fn materials_by_name() {
+		let library = "\
+newmtl red plastic
+Kd 0.8 0.1 0.1
+newmtl glass
+Ni 1.5
+illum 7
+";
+		let path = test_file("materials.mtl", library.as_bytes());
+		let materials = load_mtl(&path, &mut TextureCache::new()).unwrap();
+		assert_eq!(materials.len(), 2);
+		assert!(materials.contains_key("red plastic") && materials.contains_key("glass"));
+
+		let source = format!("mtllib {}\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl glass\nf 1 2 3\n", path.file_name().unwrap().to_str().unwrap());
+		assert_eq!(load_source("materials.obj", &source).unwrap().objects.len(), 1);
+	}
+
+	#[test]
+	fn material_statements_before_newmtl() {
+		let path = test_file("orphan.mtl", b"# comment\nKd 1 1 1\n");
+		match load_mtl(path, &mut TextureCache::new()) {
+			Err(LoadError::PARSE { line, .. }) => assert_eq!(line, Some(2)),
+			_ => panic!("expected a parse error")
+		}
+	}
+}
@@ -0,0 +1,476 @@
+use std::fs;
+use std::path::Path;
+use crate::loader::{LoadError, MeshData};
+use crate::texture::image_texture::srgb_to_linear;
+use crate::util::vec3::Vec3;
+
+// Stanford .ply import, ascii and binary of either byte order. Reads positions, faces
+// (fan triangulated) and, when present, normals, texture coordinates and vertex colors.
+// Elements other than `vertex` and `face` are skipped.
+pub fn load<P: AsRef<Path>>(filepath: P) -> Result<MeshData, LoadError> {
+	let bytes = fs::read(filepath)?;
+	let (header, body_start) = Header::parse(&bytes)?;
+
+	let mut reader: Box<dyn ValueReader> = match header.format {
+		Format::Ascii => {
+			let body = std::str::from_utf8(&bytes[body_start..])
+				.map_err(|_| LoadError::invalid("ascii ply body is not valid UTF-8"))?;
+			Box::new(AsciiReader { tokens: body.split_whitespace() })
+		}
+		Format::BinaryLittleEndian => Box::new(BinaryReader { bytes: &bytes[body_start..], position: 0, little_endian: true }),
+		Format::BinaryBigEndian => Box::new(BinaryReader { bytes: &bytes[body_start..], position: 0, little_endian: false })
+	};
+
+	let mut mesh = MeshData { positions: Vec::new(), normals: None, uvs: None, tangents: None, colors: None, faces: Vec::new() };
+
+	for element in &header.elements {
+		match element.name.as_str() {
+			"vertex" => read_vertices(element, reader.as_mut(), &mut mesh)?,
+			"face" => read_faces(element, reader.as_mut(), &mut mesh)?,
+			_ => {
+				for _ in 0..element.count {
+					for property in &element.properties {
+						property.skip(reader.as_mut())?;
+					}
+				}
+			}
+		}
+	}
+
+	mesh.validate()?;
+	Ok(mesh)
+}
+
+fn read_vertices(element: &Element, reader: &mut dyn ValueReader, mesh: &mut MeshData) -> Result<(), LoadError> {
+	let index_of = |names: &[&str]| element.properties.iter().position(|property| names.contains(&property.name.as_str()));
+
+	let position = [index_of(&["x"]), index_of(&["y"]), index_of(&["z"])];
+	let normal = [index_of(&["nx"]), index_of(&["ny"]), index_of(&["nz"])];
+	let uv = [index_of(&["u", "s", "texture_u", "texture_s"]), index_of(&["v", "t", "texture_v", "texture_t"])];
+	let color = [index_of(&["red", "r", "diffuse_red"]), index_of(&["green", "g", "diffuse_green"]), index_of(&["blue", "b", "diffuse_blue"])];
+
+	let [Some(x), Some(y), Some(z)] = position else {
+		return Err(LoadError::invalid("vertex element without x, y and z properties"));
+	};
+	let normal = if let [Some(nx), Some(ny), Some(nz)] = normal { Some((nx, ny, nz)) } else { None };
+	let uv = if let [Some(u), Some(v)] = uv { Some((u, v)) } else { None };
+	let color = if let [Some(r), Some(g), Some(b)] = color { Some((r, g, b)) } else { None };
+
+	let mut normals = Vec::new();
+	let mut uvs = Vec::new();
+	let mut colors = Vec::new();
+
+	let mut values = vec![0.0; element.properties.len()];
+	for _ in 0..element.count {
+		for (value, property) in values.iter_mut().zip(&element.properties) {
+			*value = property.read_scalar(reader)?;
+		}
+
+		mesh.positions.push(Vec3::new(values[x], values[y], values[z]));
+
+		if let Some((nx, ny, nz)) = normal {
+			normals.push(Vec3::new(values[nx], values[ny], values[nz]));
+		}
+		if let Some((u, v)) = uv {
+			uvs.push((values[u], values[v]));
+		}
+		if let Some((r, g, b)) = color {
+			// integer channels are 8 bit sRGB, float channels are taken as linear
+			let channel = |index: usize| {
+				match element.properties[index].kind {
+					ScalarType::F32 | ScalarType::F64 => values[index],
+					kind => srgb_to_linear((values[index] / kind.max_value()) as f32) as f64
+				}
+			};
+			colors.push(Vec3::new(channel(r), channel(g), channel(b)));
+		}
+	}
+
+	if normal.is_some() { mesh.normals = Some(normals); }
+	if uv.is_some() { mesh.uvs = Some(uvs); }
+	if color.is_some() { mesh.colors = Some(colors); }
+
+	Ok(())
+}
+
+fn read_faces(element: &Element, reader: &mut dyn ValueReader, mesh: &mut MeshData) -> Result<(), LoadError> {
+	let indices = element.properties.iter()
+		.position(|property| matches!(property.name.as_str(), "vertex_indices" | "vertex_index"))
+		.ok_or_else(|| LoadError::invalid("face element without a vertex_indices list"))?;
+
+	for _ in 0..element.count {
+		for (index, property) in element.properties.iter().enumerate() {
+			if index != indices {
+				property.skip(reader)?;
+				continue;
+			}
+
+			let Some(count_kind) = property.list_count else {
+				return Err(LoadError::invalid("vertex_indices is not a list"));
+			};
+
+			// the count comes from the file, so grow with the values actually read rather than trust it
+			let count = read_index(reader, count_kind)?;
+			let mut polygon = Vec::new();
+			for _ in 0..count {
+				polygon.push(read_index(reader, property.kind)?);
+			}
+
+			for i in 1..polygon.len().saturating_sub(1) {
+				mesh.faces.push([polygon[0], polygon[i], polygon[i + 1]]);
+			}
+		}
+	}
+
+	Ok(())
+}
+
+// A list count or vertex index, casting would silently turn -1 into 0 and 2.7 into 2
+fn read_index(reader: &mut dyn ValueReader, kind: ScalarType) -> Result<usize, LoadError> {
+	let value = reader.read(kind)?;
+	if !(value >= 0.0 && value.fract() == 0.0) {
+		return Err(LoadError::invalid(format!("{} is not a valid count or index", value)));
+	}
+	Ok(value as usize)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+	Ascii,
+	BinaryLittleEndian,
+	BinaryBigEndian
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum ScalarType {
+	I8,
+	U8,
+	I16,
+	U16,
+	I32,
+	U32,
+	F32,
+	F64
+}
+
+impl ScalarType {
+	fn parse(name: &str, line: usize) -> Result<Self, LoadError> {
+		match name {
+			"char" | "int8" => Ok(ScalarType::I8),
+			"uchar" | "uint8" => Ok(ScalarType::U8),
+			"short" | "int16" => Ok(ScalarType::I16),
+			"ushort" | "uint16" => Ok(ScalarType::U16),
+			"int" | "int32" => Ok(ScalarType::I32),
+			"uint" | "uint32" => Ok(ScalarType::U32),
+			"float" | "float32" => Ok(ScalarType::F32),
+			"double" | "float64" => Ok(ScalarType::F64),
+			_ => Err(LoadError::parse(line, format!("unknown property type '{}'", name)))
+		}
+	}
+
+	fn size(&self) -> usize {
+		match self {
+			ScalarType::I8 | ScalarType::U8 => 1,
+			ScalarType::I16 | ScalarType::U16 => 2,
+			ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+			ScalarType::F64 => 8
+		}
+	}
+
+	// Full scale of unsigned channels, used to normalize colors
+	fn max_value(&self) -> f64 {
+		match self {
+			ScalarType::I8 => i8::MAX as f64,
+			ScalarType::U8 => u8::MAX as f64,
+			ScalarType::I16 => i16::MAX as f64,
+			ScalarType::U16 => u16::MAX as f64,
+			ScalarType::I32 => i32::MAX as f64,
+			ScalarType::U32 => u32::MAX as f64,
+			ScalarType::F32 | ScalarType::F64 => 1.0
+		}
+	}
+}
+
+struct Property {
+	name: String,
+	kind: ScalarType,
+	// type of the length prefix for list properties
+	list_count: Option<ScalarType>
+}
+
+impl Property {
+	fn read_scalar(&self, reader: &mut dyn ValueReader) -> Result<f64, LoadError> {
+		if self.list_count.is_some() {
+			return Err(LoadError::invalid(format!("list property '{}' where a scalar was expected", self.name)));
+		}
+		reader.read(self.kind)
+	}
+
+	fn skip(&self, reader: &mut dyn ValueReader) -> Result<(), LoadError> {
+		let count = match self.list_count {
+			Some(count_kind) => read_index(reader, count_kind)?,
+			None => 1
+		};
+
+		for _ in 0..count {
+			reader.read(self.kind)?;
+		}
+
+		Ok(())
+	}
+}
+
+struct Element {
+	name: String,
+	count: usize,
+	properties: Vec<Property>
+}
+
+struct Header {
+	format: Format,
+	elements: Vec<Element>
+}
+
+impl Header {
+	// Returns the header and the offset of the first body byte
+	fn parse(bytes: &[u8]) -> Result<(Self, usize), LoadError> {
+		const END: &[u8] = b"end_header";
+
+		let end = bytes.windows(END.len())
+			.position(|window| window == END)
+			.ok_or_else(|| LoadError::invalid("missing end_header"))?;
+
+		// the body starts after the line break following end_header
+		let body_start = bytes[end..].iter()
+			.position(|&byte| byte == b'\n')
+			.map(|offset| end + offset + 1)
+			.unwrap_or(bytes.len());
+
+		let text = std::str::from_utf8(&bytes[..end])
+			.map_err(|_| LoadError::invalid("ply header is not valid UTF-8"))?;
+
+		let mut lines = text.lines().enumerate().map(|(index, line)| (index + 1, line.trim()));
+
+		match lines.next() {
+			Some((_, "ply")) => {}
+			_ => return Err(LoadError::parse(1, "not a ply file, expected 'ply'"))
+		}
+
+		let mut format = None;
+		let mut elements: Vec<Element> = Vec::new();
+
+		for (line, text) in lines {
+			let tokens: Vec<&str> = text.split_whitespace().collect();
+
+			match tokens.as_slice() {
+				[] | ["comment", ..] | ["obj_info", ..] => {}
+				["format", name, _version] => {
+					format = Some(match *name {
+						"ascii" => Format::Ascii,
+						"binary_little_endian" => Format::BinaryLittleEndian,
+						"binary_big_endian" => Format::BinaryBigEndian,
+						_ => return Err(LoadError::parse(line, format!("unknown format '{}'", name)))
+					});
+				}
+				["element", name, count] => {
+					let count = count.parse()
+						.map_err(|_| LoadError::parse(line, format!("invalid element count '{}'", count)))?;
+					elements.push(Element { name: name.to_string(), count, properties: Vec::new() });
+				}
+				["property", "list", count_kind, kind, name] => {
+					let element = elements.last_mut().ok_or_else(|| LoadError::parse(line, "property before any element"))?;
+					element.properties.push(Property {
+						name: name.to_string(),
+						kind: ScalarType::parse(kind, line)?,
+						list_count: Some(ScalarType::parse(count_kind, line)?)
+					});
+				}
+				["property", kind, name] => {
+					let element = elements.last_mut().ok_or_else(|| LoadError::parse(line, "property before any element"))?;
+					element.properties.push(Property {
+						name: name.to_string(),
+						kind: ScalarType::parse(kind, line)?,
+						list_count: None
+					});
+				}
+				_ => return Err(LoadError::parse(line, format!("unexpected header line '{}'", text)))
+			}
+		}
+
+		let format = format.ok_or_else(|| LoadError::invalid("ply header without a format line"))?;
+
+		Ok((Header { format, elements }, body_start))
+	}
+}
+
+// Source of body values, each returned as f64 whatever its stored type
+trait ValueReader {
+	fn read(&mut self, kind: ScalarType) -> Result<f64, LoadError>;
+}
+
+struct AsciiReader<'a> {
+	tokens: std::str::SplitWhitespace<'a>
+}
+
+impl ValueReader for AsciiReader<'_> {
+	fn read(&mut self, _kind: ScalarType) -> Result<f64, LoadError> {
+		let token = self.tokens.next().ok_or_else(|| LoadError::invalid("ply body ends early"))?;
+		token.parse().map_err(|_| LoadError::invalid(format!("invalid number '{}' in ply body", token)))
+	}
+}
+
+struct BinaryReader<'a> {
+	bytes: &'a [u8],
+	position: usize,
+	little_endian: bool
+}
+
+impl ValueReader for BinaryReader<'_> {
+	fn read(&mut self, kind: ScalarType) -> Result<f64, LoadError> {
+		let size = kind.size();
+		let slice = self.bytes.get(self.position..self.position + size)
+			.ok_or_else(|| LoadError::invalid("ply body ends early"))?;
+		self.position += size;
+
+		let mut buffer = [0u8; 8];
+		buffer[..size].copy_from_slice(slice);
+		if !self.little_endian { buffer[..size].reverse(); }
+
+		let value = match kind {
+			ScalarType::I8 => buffer[0] as i8 as f64,
+			ScalarType::U8 => buffer[0] as f64,
+			ScalarType::I16 => i16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+			ScalarType::U16 => u16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+			ScalarType::I32 => i32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+			ScalarType::U32 => u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+			ScalarType::F32 => f32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+			ScalarType::F64 => f64::from_le_bytes(buffer)
+		};
+
+		Ok(value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::loader::test_file;
+
+	const TRIANGLE_HEADER: &str = "\
+element vertex 3
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+	fn binary(little_endian: bool) -> Vec<u8> {
+		let format = if little_endian { "binary_little_endian" } else { "binary_big_endian" };
+		let mut bytes = format!("ply\nformat {} 1.0\n{}", format, TRIANGLE_HEADER).into_bytes();
+
+		for value in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, 0.0] {
+			bytes.extend(if little_endian { value.to_le_bytes() } else { value.to_be_bytes() });
+		}
+		bytes.push(3);
+		for index in [0i32, 1, 2] {
+			bytes.extend(if little_endian { index.to_le_bytes() } else { index.to_be_bytes() });
+		}
+
+		bytes
+	}
+
+	fn assert_invalid(result: Result<MeshData, LoadError>) {
+		assert!(matches!(result, Err(LoadError::PARSE { .. })), "expected a parse error");
+	}
+
+	#[test]
+	fn ascii_with_attributes() {
+		let source = "\
+ply
+format ascii 1.0
+comment colored quad
+element vertex 4
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property float s
+property float t
+property uchar red
+property uchar green
+property uchar blue
+element edge 1
+property int vertex1
+property int vertex2
+element face 1
+property uchar flags
+property list uchar int vertex_indices
+end_header
+0 0 0 0 0 1 0 0 255 0 0
+1 0 0 0 0 1 1 0 0 255 0
+1 1 0 0 0 1 1 1 0 0 255
+0 1 0 0 0 1 0 1 255 255 255
+0 2
+7 4 0 1 2 3
+";
+		let mesh = load(test_file("quad.ply", source.as_bytes())).unwrap();
+
+		assert_eq!(mesh.positions.len(), 4);
+		assert_eq!(mesh.faces, vec![[0, 1, 2], [0, 2, 3]]);
+		assert_eq!(mesh.uvs.unwrap()[2], (1.0, 1.0));
+		assert!((mesh.normals.unwrap()[1] - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-12);
+
+		let colors = mesh.colors.unwrap();
+		assert!((colors[0] - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-6);
+		assert!((colors[3] - Vec3::new(1.0, 1.0, 1.0)).length() < 1e-6);
+	}
+
+	#[test]
+	fn binary_of_either_byte_order() {
+		for little_endian in [true, false] {
+			let mesh = load(test_file(&format!("binary-{}.ply", little_endian), &binary(little_endian))).unwrap();
+
+			assert_eq!(mesh.faces, vec![[0, 1, 2]]);
+			assert!((mesh.positions[2] - Vec3::new(0.0, 2.0, 0.0)).length() < 1e-12);
+			assert!(mesh.normals.is_none() && mesh.colors.is_none());
+		}
+	}
+
+	#[test]
+	fn truncated_body() {
+		let mut bytes = binary(true);
+		bytes.truncate(bytes.len() - 2);
+		assert_invalid(load(test_file("truncated.ply", &bytes)));
+	}
+
+	#[test]
+	fn huge_list_count() {
+		// the count claims four billion indices, the body ends after one
+		let source = format!("ply\nformat ascii 1.0\n{}0 0 0\n1 0 0\n0 1 0\n4000000000 0\n", TRIANGLE_HEADER.replace("uchar int", "uint int"));
+		assert_invalid(load(test_file("huge.ply", source.as_bytes())));
+	}
+
+	#[test]
+	fn malformed_headers_and_indices() {
+		let out_of_range = format!("ply\nformat ascii 1.0\n{}0 0 0\n1 0 0\n0 1 0\n3 0 1 3\n", TRIANGLE_HEADER);
+		assert_invalid(load(test_file("range.ply", out_of_range.as_bytes())));
+
+		assert_invalid(load(test_file("magic.ply", b"obj\nformat ascii 1.0\nend_header\n")));
+		assert_invalid(load(test_file("unterminated.ply", b"ply\nformat ascii 1.0\n")));
+		assert_invalid(load(test_file("format.ply", b"ply\nelement vertex 0\nend_header\n")));
+		assert_invalid(load(test_file("type.ply", b"ply\nformat ascii 1.0\nelement vertex 1\nproperty half x\nend_header\n")));
+	}
+
+	#[test]
+	fn non_integer_indices() {
+		// casting would turn -1 into 0 and 1.5 into 1, both of which are in range
+		for (name, indices) in [("negative.ply", "3 -1 1 2"), ("fraction.ply", "3 0 1.5 2"), ("nan.ply", "3 0 1 nan"), ("count.ply", "2.5 0 1 2")] {
+			let source = format!("ply\nformat ascii 1.0\n{}0 0 0\n1 0 0\n0 1 0\n{}\n", TRIANGLE_HEADER, indices);
+			assert_invalid(load(test_file(name, source.as_bytes())));
+		}
+	}
+}
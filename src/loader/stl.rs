@@ -0,0 +1,194 @@
+use std::fs;
+use std::path::Path;
+use crate::loader::{LoadError, MeshData};
+use crate::util::vec3::Vec3;
+
+// Size of the binary header and of one binary facet record
+const HEADER_SIZE: usize = 80;
+const FACET_SIZE: usize = 50;
+
+// STL import, ascii or binary. Facets do not share vertices, each corner gets the facet normal.
+pub fn load<P: AsRef<Path>>(filepath: P) -> Result<MeshData, LoadError> {
+	let bytes = fs::read(filepath)?;
+
+	// some binary exporters also start their header with "solid",
+	// so the size matching the facet count decides
+	if is_binary(&bytes) {
+		load_binary(&bytes)
+	} else if bytes.starts_with(b"solid") {
+		load_ascii(&bytes)
+	} else {
+		Err(LoadError::invalid("neither an ascii STL (missing 'solid') nor a binary one (size does not match the facet count)"))
+	}
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+	if bytes.len() < HEADER_SIZE + 4 { return false; }
+
+	let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+	bytes.len() == HEADER_SIZE + 4 + count * FACET_SIZE
+}
+
+fn load_binary(bytes: &[u8]) -> Result<MeshData, LoadError> {
+	let mut mesh = empty_mesh();
+
+	for facet in bytes[HEADER_SIZE + 4..].chunks_exact(FACET_SIZE) {
+		let vector = |offset: usize| {
+			let component = |index: usize| {
+				let start = offset + 4 * index;
+				f32::from_le_bytes([facet[start], facet[start + 1], facet[start + 2], facet[start + 3]]) as f64
+			};
+			Vec3::new(component(0), component(1), component(2))
+		};
+
+		add_facet(&mut mesh, vector(0), [vector(12), vector(24), vector(36)]);
+	}
+
+	Ok(mesh)
+}
+
+fn load_ascii(bytes: &[u8]) -> Result<MeshData, LoadError> {
+	let text = std::str::from_utf8(bytes).map_err(|_| LoadError::invalid("ascii STL is not valid UTF-8"))?;
+	let mut mesh = empty_mesh();
+
+	let mut normal = Vec3::ZERO;
+	let mut corners: Vec<Vec3> = Vec::with_capacity(3);
+
+	for (index, line) in text.lines().enumerate() {
+		let line_number = index + 1;
+		let tokens: Vec<&str> = line.split_whitespace().collect();
+
+		match tokens.as_slice() {
+			["facet", "normal", x, y, z] => {
+				normal = parse_vec3([x, y, z], line_number)?;
+				corners.clear();
+			}
+			["vertex", x, y, z] => {
+				if corners.len() == 3 {
+					return Err(LoadError::parse(line_number, "facet with more than 3 vertices"));
+				}
+				corners.push(parse_vec3([x, y, z], line_number)?);
+			}
+			["endfacet"] => {
+				let [a, b, c] = corners[..] else {
+					return Err(LoadError::parse(line_number, format!("facet with {} vertices", corners.len())));
+				};
+				add_facet(&mut mesh, normal, [a, b, c]);
+			}
+			_ => {}
+		}
+	}
+
+	Ok(mesh)
+}
+
+fn empty_mesh() -> MeshData {
+	MeshData { positions: Vec::new(), normals: Some(Vec::new()), uvs: None, tangents: None, colors: None, faces: Vec::new() }
+}
+
+fn add_facet(mesh: &mut MeshData, normal: Vec3, corners: [Vec3; 3]) {
+	let first = mesh.positions.len();
+
+	// many exporters write a zero normal and rely on the winding
+	let normal = if normal.is_near_zero() {
+		(corners[1] - corners[0]).cross(corners[2] - corners[0])
+	} else {
+		normal
+	};
+	let normal = if normal.is_near_zero() { normal } else { normal.unit() };
+
+	mesh.positions.extend(corners);
+	if let Some(normals) = &mut mesh.normals {
+		normals.extend([normal; 3]);
+	}
+	mesh.faces.push([first, first + 1, first + 2]);
+}
+
+fn parse_vec3(tokens: [&&str; 3], line: usize) -> Result<Vec3, LoadError> {
+	let parse = |token: &str| token.parse::<f64>()
+		.map_err(|_| LoadError::parse(line, format!("invalid number '{}'", token)));
+
+	Ok(Vec3::new(parse(tokens[0])?, parse(tokens[1])?, parse(tokens[2])?))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::loader::test_file;
+
+	fn binary(header: &[u8], facets: &[(Vec3, [Vec3; 3])]) -> Vec<u8> {
+		let mut bytes = header.to_vec();
+		bytes.resize(HEADER_SIZE, 0);
+		bytes.extend((facets.len() as u32).to_le_bytes());
+
+		for (normal, corners) in facets {
+			for vector in [*normal, corners[0], corners[1], corners[2]] {
+				for component in [vector.x, vector.y, vector.z] {
+					bytes.extend((component as f32).to_le_bytes());
+				}
+			}
+			// attribute byte count
+			bytes.extend([0, 0]);
+		}
+
+		bytes
+	}
+
+	#[test]
+	fn ascii_facets() {
+		let source = "\
+solid test
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+  facet normal 0 0 0
+    outer loop
+      vertex 0 0 0
+      vertex 0 1 0
+      vertex 1 0 0
+    endloop
+  endfacet
+endsolid test
+";
+		let mesh = load(test_file("facets.stl", source.as_bytes())).unwrap();
+		assert_eq!(mesh.positions.len(), 6);
+		assert_eq!(mesh.faces, vec![[0, 1, 2], [3, 4, 5]]);
+
+		// the zero normal comes from the clockwise winding instead
+		let normals = mesh.normals.unwrap();
+		assert!((normals[0] - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-12);
+		assert!((normals[3] - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-12);
+	}
+
+	#[test]
+	fn binary_with_solid_header() {
+		let corners = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -2.0)];
+		let bytes = binary(b"solid exported in binary", &[(Vec3::new(0.0, 1.0, 0.0), corners); 2]);
+		let mesh = load(test_file("binary.stl", &bytes)).unwrap();
+
+		assert_eq!(mesh.faces, vec![[0, 1, 2], [3, 4, 5]]);
+		assert!((mesh.positions[4] - corners[1]).length() < 1e-12);
+		assert!((mesh.normals.unwrap()[5] - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-12);
+	}
+
+	#[test]
+	fn malformed_files() {
+		let four = "solid\nfacet normal 0 0 1\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nvertex 1 1 0\nendfacet\n";
+		match load(test_file("four.stl", four.as_bytes())) {
+			Err(LoadError::PARSE { line, .. }) => assert_eq!(line, Some(6)),
+			_ => panic!("expected a parse error")
+		}
+
+		let two = "solid\nfacet normal 0 0 1\nvertex 0 0 0\nvertex 1 0 0\nendfacet\n";
+		assert!(load(test_file("two.stl", two.as_bytes())).is_err());
+
+		// the facet count promises more than the file holds
+		let mut bytes = binary(b"", &[(Vec3::ZERO, [Vec3::ZERO; 3])]);
+		bytes.truncate(bytes.len() - 1);
+		assert!(load(test_file("short.stl", &bytes)).is_err());
+	}
+}
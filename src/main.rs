@@ -7,14 +7,17 @@ mod material;
 mod AABB;
 mod texture;
 mod ONB;
+mod loader;
 
 use std::error::Error;
 use std::f64::consts::PI;
 use crate::camera::{Background, Camera, SampleSettings, HDRI};
-use crate::hittable::hittable::{Hittable, HittableList, RotateY, Translate};
-use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::hittable::hittable::{AnimatedInstance, Hittable, HittableList, RotateY, TransformInstance, Translate};
+use crate::material::{BumpMapped, Cloth, Dielectric, DiffuseLight, Isotropic, Lambertian, Material, Metal, NormalMapped, ThinDielectric, ThinFilm};
 use crate::hittable::sphere::Sphere;
+use crate::hittable::torus::Torus;
 use crate::util::util::{random_f64, random_vector};
+use crate::util::transform::{AnimatedTransform, Keyframe, Quaternion, Transform};
 use crate::util::vec3::Vec3;
 use log::{info, LevelFilter};
 use std::fs::File;
@@ -22,10 +25,26 @@ use std::io::{BufReader, Write};
 use std::sync::Arc;
 use image::error::UnsupportedErrorKind::Format;
 use crate::hittable::BVH::BVHNode;
+use crate::hittable::cone::Cone;
+use crate::hittable::cylinder::Cylinder;
+use crate::hittable::disk::{Annulus, Disk};
+use crate::hittable::instance::Instance;
+use crate::hittable::mesh::{MeshBuffers, TriangleMesh};
 use crate::hittable::quad::Quad;
-use crate::hittable::triangle::Triangle;
-use crate::texture::{CheckeredTexture, ImageTexture, NoiseTexture, SolidColorTexture};
-use crate::hittable::volume::ConstantMedium;
+use crate::hittable::triangle::{Culling, Triangle};
+use crate::texture::cache::TextureCache;
+use crate::texture::debug::{GridTexture, UVCheckerTexture, UVGradientTexture};
+use crate::texture::texture::{CheckeredTexture, NoiseTexture, SolidColorTexture, Texture, VertexColorTexture};
+use crate::hittable::volume::{ConstantMedium, HeterogeneousMedium};
+use crate::texture::procedural::{ColorRamp, NoiseSettings, Pattern, ProceduralTexture};
+use crate::texture::voxel::{VoxelFormat, VoxelGrid, VoxelTexture};
+use crate::hittable::alpha::{AlphaMask, AlphaMode};
+use crate::texture::image_texture::{ColorSpace, Filter, ImageSettings, ImageTexture, MipFilter, WrapMode};
+use crate::hittable::displacement::Displacement;
+use crate::texture::composite::{AddTexture, ClampTexture, HSVTexture, InvertTexture, MixTexture, MultiplyTexture, PositionTransformTexture, RampTexture, UVTransformTexture};
+use crate::texture::projection::{CylindricalProjectionTexture, PlanarProjectionTexture, SphericalProjectionTexture, TriplanarTexture};
+use std::path::Path;
+use crate::loader::{gltf, obj, ply, stl};
 
 fn main() -> Result<(), Box<dyn Error>> {
 
@@ -39,21 +58,48 @@ fn main() -> Result<(), Box<dyn Error>> {
 
 	const SCENE: u8 = 11;
 
-	match SCENE {
+	// shared by every scene loading image files
+	let mut texture_cache = TextureCache::new();
+
+	let result = match SCENE {
+		29 => culling(&mut image_file),
+		28 => model(&mut image_file, &mut texture_cache, "model.obj"),
+		27 => voxel_smoke(&mut image_file),
+		26 => projections(&mut image_file, &mut texture_cache),
+		25 => texture_composition(&mut image_file, &mut texture_cache),
+		24 => procedural_patterns(&mut image_file),
+		23 => mip_filtering(&mut image_file, &mut texture_cache),
+		22 => texture_sampling(&mut image_file, &mut texture_cache),
+		21 => displaced(&mut image_file),
+		20 => bump_maps(&mut image_file),
+		19 => cutouts(&mut image_file),
+		18 => fabrics(&mut image_file),
+		17 => primitives(&mut image_file),
+		16 => motion_blur(&mut image_file, &mut texture_cache),
+		15 => forest(&mut image_file),
+		14 => clouds(&mut image_file),
+		13 => uv_debug(&mut image_file),
+		12 => thin_films(&mut image_file),
 		11 => hdri(&mut image_file),
 		10 => triangles(&mut image_file),
-		9 => final_scene(&mut image_file, 800, 40),
-		8 => final_scene(&mut image_file, 400, 50),
+		9 => final_scene(&mut image_file, &mut texture_cache, 800, 40),
+		8 => final_scene(&mut image_file, &mut texture_cache, 400, 50),
 		7 => cornell_smoke(&mut image_file),
 		6 => cornell_box(&mut image_file),
 		5 => simple_light(&mut image_file),
 		4 => quads(&mut image_file),
 		3 => perlin_spheres(&mut image_file),
-		2 => earth(&mut image_file),
+		2 => earth(&mut image_file, &mut texture_cache),
 		1 => checkered_spheres(&mut image_file),
 		_ => bouncing_spheres(&mut image_file),
+	};
+
+	if !texture_cache.is_empty() {
+		texture_cache.log_usage();
 	}
 
+	result
+
 }
 
 // SCENES //
@@ -214,11 +260,11 @@ fn checkered_spheres(image_file: &mut File) -> Result<(), Box<dyn Error>> {
 	Ok(())
 }
 
-fn earth(image_file: &mut File) -> Result<(), Box<dyn Error>> {
+fn earth(image_file: &mut File, texture_cache: &mut TextureCache) -> Result<(), Box<dyn Error>> {
 
 	let mut world = HittableList::new();
 
-	let earth_texture = Arc::new(ImageTexture::new("earthmap.jpg")?);
+	let earth_texture = texture_cache.load("earthmap.jpg")?;
 	let earth_material = Arc::new(Lambertian::from_texture(earth_texture));
 
 	world.add(Box::new(Sphere::new_stationary(
@@ -625,6 +671,7 @@ fn cornell_smoke(image_file: &mut File) -> Result<(), Box<dyn Error>> {
 
 fn final_scene(
 	image_file: &mut File,
+	texture_cache: &mut TextureCache,
 	image_width: i32,
 	max_depth: u32
 ) -> Result<(), Box<dyn Error>> {
@@ -693,7 +740,7 @@ fn final_scene(
 	)));
 
 	// EARTH //
-	let earth_texture = Arc::new(ImageTexture::new("earthmap.jpg")?);
+	let earth_texture = texture_cache.load("earthmap.jpg")?;
 	let earth_material = Arc::new(Lambertian::from_texture(earth_texture));
 	world.add(Box::new(Sphere::new_stationary(
 		Vec3::new(400.0, 200.0, 400.0),
@@ -885,4 +932,1334 @@ fn triangles(image_file: &mut File) -> Result<(), Box<dyn Error>> {
 	camera.render(world_bvh, image_file)?;
 
 	Ok(())
-}
\ No newline at end of file
+}
+
+fn thin_films(image_file: &mut File) -> Result<(), Box<dyn Error>> {
+
+	let mut world = HittableList::new();
+
+	let ground_texture = Arc::new(CheckeredTexture::from_colors(
+		0.5,
+		Vec3::new(0.2, 0.3, 0.1),
+		Vec3::new(0.9, 0.9, 0.9)
+	));
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(0.0, -1000.0, 0.0),
+		1000.0,
+		Arc::new(Lambertian::from_texture(ground_texture))
+	)));
+
+	// soap bubble: a water film with air on both sides
+	let bubble_material = Arc::new(ThinDielectric::with_thin_film(
+		1.0,
+		ThinFilm::new(380.0, 1.33)
+	));
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(-2.2, 1.0, 0.0),
+		1.0,
+		bubble_material
+	)));
+
+	// coated lens
+	let lens_material = Arc::new(Dielectric::with_thin_film(
+		1.5,
+		ThinFilm::new(120.0, 1.38)
+	));
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(0.0, 1.0, 0.0),
+		1.0,
+		lens_material
+	)));
+
+	// oxidised metal
+	let oxide_material = Arc::new(Metal::with_thin_film(
+		Vec3::new(0.8, 0.8, 0.8),
+		0.05,
+		ThinFilm::new(300.0, 2.0)
+	));
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(2.2, 1.0, 0.0),
+		1.0,
+		oxide_material
+	)));
+
+	// window pane in front of everything
+	world.add(Box::new(Quad::new(
+		Vec3::new(-3.5, 0.0, 2.0),
+		Vec3::new(7.0, 0.0, 0.0),
+		Vec3::new(0.0, 2.5, 0.0),
+		Arc::new(ThinDielectric::new(1.5))
+	)));
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		30.0,
+		Vec3::new(0.0, 2.0, 12.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.7, 0.8, 1.0))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn uv_debug(image_file: &mut File) -> Result<(), Box<dyn Error>> {
+
+	let mut world = HittableList::new();
+
+	// the gradient under the checks and the grid lines shows which way u and v run
+	let gradient = Arc::new(UVGradientTexture::new());
+
+	let checker = Arc::new(UVCheckerTexture::new(
+		16.0,
+		8.0,
+		gradient.clone(),
+		Arc::new(SolidColorTexture::new(Vec3::new(0.1, 0.1, 0.1)))
+	));
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(-2.2, 0.0, 0.0),
+		1.0,
+		Arc::new(Lambertian::from_texture(checker))
+	)));
+
+	let grid = Arc::new(GridTexture::new(
+		8.0,
+		0.08,
+		Arc::new(SolidColorTexture::new(Vec3::new(0.1, 0.1, 0.1))),
+		gradient.clone()
+	));
+	world.add(Box::new(Quad::new(
+		Vec3::new(-1.0, -1.0, 0.0),
+		Vec3::new(2.0, 0.0, 0.0),
+		Vec3::new(0.0, 2.0, 0.0),
+		Arc::new(Lambertian::from_texture(grid))
+	)));
+
+	world.add(Box::new(Triangle::new(
+		Vec3::new(1.4, 1.0, 0.0),
+		Vec3::new(3.4, -1.0, 0.0),
+		Vec3::new(1.4, -1.0, 0.0),
+		Arc::new(Lambertian::from_texture(gradient))
+	)));
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		40.0,
+		Vec3::new(0.0, 0.0, 8.0),
+		Vec3::new(0.0, 0.0, 0.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.7, 0.8, 1.0))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn clouds(image_file: &mut File) -> Result<(), Box<dyn Error>> {
+	// the struct, not the module of the same name declared above
+	use crate::AABB::AABB;
+
+	let mut world = HittableList::new();
+
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(0.0, -1000.0, 0.0),
+		1000.0,
+		Arc::new(Lambertian::from_color(Vec3::new(0.4, 0.5, 0.3)))
+	)));
+
+	// CLOUD //
+	// fbm noise faded out towards the edge of a sphere
+	let cloud_noise = ProceduralTexture::new(
+		Pattern::FBM,
+		NoiseSettings { scale: 1.5, warp: 0.5, ..NoiseSettings::default() },
+		ColorRamp::grayscale()
+	);
+	let cloud_bounds = AABB::from_corners(Vec3::new(-4.0, 2.0, -2.0), Vec3::new(0.0, 5.0, 2.0));
+	let cloud_center = Vec3::new(-2.0, 3.5, 0.0);
+	let cloud_grid = Arc::new(VoxelGrid::from_scalar_fn((64, 48, 64), cloud_bounds, |p| {
+		let falloff = 1.0 - (p - cloud_center).length() / 2.0;
+		f64::max(0.0, cloud_noise.scalar_at(p) - 0.45 + 0.6 * falloff)
+	}));
+	world.add(Box::new(HeterogeneousMedium::from_grid(
+		cloud_grid,
+		8.0,
+		Arc::new(Isotropic::from_color(Vec3::new(0.9, 0.9, 0.9)))
+	)));
+
+	// FIRE //
+	// density and glow both fade with height, the glow shifts from yellow to dark red
+	let fire_bounds = AABB::from_corners(Vec3::new(1.0, 0.0, -1.0), Vec3::new(3.0, 3.0, 1.0));
+	let fire_noise = ProceduralTexture::new(
+		Pattern::RIDGED,
+		NoiseSettings { scale: 2.0, ..NoiseSettings::default() },
+		ColorRamp::grayscale()
+	);
+	let flame_center = Vec3::new(2.0, 0.0, 0.0);
+	let fire_grid = Arc::new(VoxelGrid::from_scalar_fn((48, 72, 48), fire_bounds, |p| {
+		let height = p.y / 3.0;
+		let radius = f64::sqrt((p.x - flame_center.x).powi(2) + (p.z - flame_center.z).powi(2));
+		let width = 0.8 * (1.0 - height);
+		if radius > width { return 0.0; }
+		(1.0 - radius / width) * fire_noise.scalar_at(p - Vec3::new(0.0, 2.0 * height, 0.0))
+	}));
+	let temperature = ColorRamp::new(vec![
+		(0.0, Vec3::ZERO),
+		(0.2, Vec3::new(0.8, 0.1, 0.0)),
+		(0.5, Vec3::new(4.0, 1.5, 0.2)),
+		(1.0, Vec3::new(8.0, 6.0, 2.0))
+	]);
+	let fire_glow = VoxelGrid::from_scalar_fn((48, 72, 48), fire_bounds, |p| fire_grid.sample_scalar(p) * (1.0 - p.y / 3.0));
+	let fire_emission = VoxelGrid::from_fn((48, 72, 48), fire_bounds, |p| temperature.sample(fire_glow.sample_scalar(p)));
+	world.add(Box::new(HeterogeneousMedium::from_grid(
+		fire_grid,
+		6.0,
+		Arc::new(Isotropic::with_emission(
+			Arc::new(SolidColorTexture::new(Vec3::new(0.2, 0.2, 0.2))),
+			Arc::new(VoxelTexture::new(Arc::new(fire_emission)))
+		))
+	)));
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		40.0,
+		Vec3::new(0.0, 3.0, 12.0),
+		Vec3::new(0.0, 2.5, 0.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.5, 0.6, 0.8))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn forest(image_file: &mut File) -> Result<(), Box<dyn Error>> {
+
+	let mut world = HittableList::new();
+
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(0.0, -1000.0, 0.0),
+		1000.0,
+		Arc::new(Lambertian::from_color(Vec3::new(0.45, 0.4, 0.3)))
+	)));
+
+	// TREE //
+	// one trunk and one foliage mesh, shared by every tree
+	let trunk: Arc<dyn Hittable> = Arc::new(Quad::cube(
+		Vec3::new(-0.1, 0.0, -0.1),
+		Vec3::new(0.1, 0.6, 0.1),
+		Arc::new(Lambertian::from_color(Vec3::new(0.3, 0.2, 0.1)))
+	));
+
+	// two stacked cones around the y axis
+	let segments = 12;
+	let mut positions = Vec::new();
+	let mut faces = Vec::new();
+	for (base, radius, height) in [(0.5, 0.8, 1.4), (1.2, 0.6, 1.2)] {
+		let apex = positions.len() as u32;
+		positions.push(Vec3::new(0.0, base + height, 0.0));
+		for i in 0..segments {
+			let angle = 2.0 * PI * i as f64 / segments as f64;
+			positions.push(Vec3::new(radius * angle.cos(), base, radius * angle.sin()));
+		}
+		for i in 0..segments {
+			faces.push([apex + 1 + i, apex, apex + 1 + (i + 1) % segments]);
+		}
+	}
+	let foliage: Arc<dyn Hittable> = Arc::new(TriangleMesh::new(
+		MeshBuffers::new(positions, faces),
+		Arc::new(Lambertian::from_color(Vec3::new(0.1, 0.4, 0.1)))
+	));
+
+	let greens: Vec<Arc<dyn Material>> = vec![
+		Arc::new(Lambertian::from_color(Vec3::new(0.1, 0.35, 0.1))),
+		Arc::new(Lambertian::from_color(Vec3::new(0.15, 0.45, 0.1))),
+		Arc::new(Lambertian::from_color(Vec3::new(0.05, 0.3, 0.15))),
+		Arc::new(Lambertian::from_color(Vec3::new(0.3, 0.4, 0.1)))
+	];
+
+	// FOREST //
+	// a thousand trees jittered on a grid, each with its own size, turn and shade
+	let mut trees = HittableList::new();
+	for row in 0..25 {
+		for column in 0..40 {
+			let x = -20.0 + column as f64 + random_f64(0.0, 0.8);
+			let z = 4.0 - 1.6 * row as f64 + random_f64(0.0, 1.2);
+
+			let placement = Transform::uniform_scale(random_f64(0.7, 1.3))
+				.then(&Transform::rotate_y(random_f64(0.0, 360.0)))
+				.then(&Transform::translate(Vec3::new(x, 0.0, z)));
+			let shade = greens[fastrand::usize(..greens.len())].clone();
+
+			trees.add(Box::new(Instance::new(trunk.clone(), placement)));
+			trees.add(Box::new(Instance::new(foliage.clone(), placement).with_material(shade)));
+		}
+	}
+	world.add(BVHNode::from_list(trees));
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		40.0,
+		Vec3::new(0.0, 4.0, 12.0),
+		Vec3::new(0.0, 1.0, -6.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.7, 0.8, 1.0))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn motion_blur(image_file: &mut File, texture_cache: &mut TextureCache) -> Result<(), Box<dyn Error>> {
+
+	let mut world = HittableList::new();
+
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(0.0, -1000.0, 0.0),
+		1000.0,
+		Arc::new(Lambertian::from_texture(Arc::new(CheckeredTexture::from_colors(
+			0.5,
+			Vec3::new(0.2, 0.3, 0.1),
+			Vec3::new(0.9, 0.9, 0.9)
+		))))
+	)));
+
+	// spinning in place
+	let earth_texture = texture_cache.load("earthmap.jpg")?;
+	let earth = Box::new(Sphere::new_stationary(Vec3::ZERO, 1.0, Arc::new(Lambertian::from_texture(earth_texture))));
+	world.add(Box::new(AnimatedInstance::new(earth, AnimatedTransform::new(vec![
+		Keyframe { translation: Vec3::new(-2.5, 1.0, 0.0), ..Keyframe::new(0.0) },
+		Keyframe {
+			translation: Vec3::new(-2.5, 1.0, 0.0),
+			rotation: Quaternion::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), 60.0),
+			..Keyframe::new(1.0)
+		}
+	]))));
+
+	// tumbling along a bent path
+	let cube = Box::new(Quad::cube(
+		Vec3::new(-0.5, -0.5, -0.5),
+		Vec3::new(0.5, 0.5, 0.5),
+		Arc::new(Metal::new(Vec3::new(0.8, 0.6, 0.2), 0.1))
+	));
+	world.add(Box::new(AnimatedInstance::new(cube, AnimatedTransform::new(vec![
+		Keyframe { translation: Vec3::new(0.0, 0.5, 0.0), ..Keyframe::new(0.0) },
+		Keyframe {
+			translation: Vec3::new(1.0, 1.5, 0.0),
+			rotation: Quaternion::from_axis_angle(Vec3::new(1.0, 1.0, 0.0), 45.0),
+			..Keyframe::new(0.5)
+		},
+		Keyframe {
+			translation: Vec3::new(2.0, 0.5, 0.0),
+			rotation: Quaternion::from_axis_angle(Vec3::new(1.0, 1.0, 0.0), 90.0),
+			..Keyframe::new(1.0)
+		}
+	]))));
+
+	// growing
+	let glass = Box::new(Sphere::new_stationary(Vec3::ZERO, 1.0, Arc::new(Dielectric::new(1.5))));
+	world.add(Box::new(AnimatedInstance::new(glass, AnimatedTransform::new(vec![
+		Keyframe { translation: Vec3::new(0.0, 0.6, 2.0), scale: Vec3::new(0.3, 0.3, 0.3), ..Keyframe::new(0.0) },
+		Keyframe { translation: Vec3::new(0.0, 0.6, 2.0), scale: Vec3::new(0.6, 0.6, 0.6), ..Keyframe::new(1.0) }
+	]))));
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		30.0,
+		Vec3::new(0.0, 3.0, 12.0),
+		Vec3::new(0.0, 0.8, 0.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.7, 0.8, 1.0))
+	).with_shutter(0.0, 1.0);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn primitives(image_file: &mut File) -> Result<(), Box<dyn Error>> {
+
+	let mut world = HittableList::new();
+
+	world.add(Box::new(Quad::new(
+		Vec3::new(-20.0, 0.0, 20.0),
+		Vec3::new(40.0, 0.0, 0.0),
+		Vec3::new(0.0, 0.0, -40.0),
+		Arc::new(Lambertian::from_color(Vec3::new(0.6, 0.6, 0.6)))
+	)));
+
+	let checker = Arc::new(Lambertian::from_texture(Arc::new(UVCheckerTexture::from_colors(
+		16.0,
+		8.0,
+		Vec3::new(0.9, 0.3, 0.2),
+		Vec3::new(0.9, 0.9, 0.9)
+	))));
+
+	// BACK ROW //
+	world.add(Box::new(Cylinder::new(Vec3::new(-3.0, 0.0, -2.0), 0.7, 1.6, checker.clone())));
+	world.add(Box::new(Cone::new(Vec3::new(0.0, 0.0, -2.0), 0.8, 1.8, checker.clone())));
+	world.add(Box::new(Torus::new(Vec3::new(3.0, 0.4, -2.0), 0.8, 0.4, checker.clone())));
+
+	// FRONT ROW //
+	world.add(Box::new(Disk::new(
+		Vec3::new(-3.0, 0.9, 1.0),
+		Vec3::new(0.0, 0.5, 1.0),
+		0.8,
+		checker.clone()
+	)));
+	world.add(Box::new(Annulus::new(
+		Vec3::new(-1.0, 0.9, 1.0),
+		Vec3::new(0.0, 0.5, 1.0),
+		0.4,
+		0.8,
+		Arc::new(Metal::new(Vec3::new(0.8, 0.7, 0.5), 0.05))
+	)));
+
+	// open glass tube lying on its side
+	let tube = Box::new(Cylinder::new(Vec3::new(0.0, -1.0, 0.0), 0.5, 2.0, Arc::new(Dielectric::new(1.5))).without_caps());
+	world.add(Box::new(TransformInstance::new(
+		tube,
+		Transform::rotate_z(90.0).then(&Transform::rotate_y(30.0)).then(&Transform::translate(Vec3::new(1.2, 0.5, 1.0)))
+	)));
+
+	// upside down open metal cone, a funnel showing its inside
+	let cone = Box::new(Cone::new(Vec3::ZERO, 0.6, 1.2, Arc::new(Metal::new(Vec3::new(0.7, 0.7, 0.8), 0.0))).without_cap());
+	world.add(Box::new(TransformInstance::new(
+		cone,
+		Transform::rotate_x(180.0).then(&Transform::translate(Vec3::new(3.2, 1.2, 1.0)))
+	)));
+
+	world.add(Box::new(Quad::new(
+		Vec3::new(-3.0, 6.0, -1.0),
+		Vec3::new(6.0, 0.0, 0.0),
+		Vec3::new(0.0, 0.0, 3.0),
+		Arc::new(DiffuseLight::from_color(Vec3::new(3.0, 3.0, 3.0)))
+	)));
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		35.0,
+		Vec3::new(0.0, 4.0, 11.0),
+		Vec3::new(0.0, 0.6, 0.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.5, 0.6, 0.7))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn fabrics(image_file: &mut File) -> Result<(), Box<dyn Error>> {
+
+	let mut world = HittableList::new();
+
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(0.0, -1000.0, 0.0),
+		1000.0,
+		Arc::new(Lambertian::from_color(Vec3::new(0.5, 0.5, 0.5)))
+	)));
+
+	// velvet: dark base with a bright, tight sheen at the silhouette
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(-2.2, 1.0, 0.0),
+		1.0,
+		Arc::new(Cloth::from_colors(Vec3::new(0.3, 0.02, 0.05), Vec3::new(0.9, 0.4, 0.5), 0.3))
+	)));
+
+	// cotton: broad, faint sheen
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(0.0, 1.0, 0.0),
+		1.0,
+		Arc::new(Cloth::from_colors(Vec3::new(0.15, 0.25, 0.5), Vec3::new(0.4, 0.4, 0.4), 0.9))
+	)));
+
+	// checkered fabric, the sheen follows the weave
+	let weave = Arc::new(CheckeredTexture::from_colors(
+		0.1,
+		Vec3::new(0.6, 0.5, 0.1),
+		Vec3::new(0.2, 0.3, 0.1)
+	));
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(2.2, 1.0, 0.0),
+		1.0,
+		Arc::new(Cloth::new(weave.clone(), weave, 0.5))
+	)));
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		30.0,
+		Vec3::new(0.0, 2.0, 12.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.7, 0.8, 1.0))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn cutouts(image_file: &mut File) -> Result<(), Box<dyn Error>> {
+
+	let mut world = HittableList::new();
+
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(0.0, -1000.0, 0.0),
+		1000.0,
+		Arc::new(Lambertian::from_color(Vec3::new(0.4, 0.5, 0.3)))
+	)));
+
+	// wire fence: only the grid lines are solid, so the sky and the shadows show through the holes
+	let wire = Arc::new(GridTexture::from_colors(
+		12.0,
+		0.2,
+		Vec3::new(1.0, 1.0, 1.0),
+		Vec3::ZERO
+	));
+	world.add(Box::new(Quad::new(
+		Vec3::new(-3.0, 0.0, 1.0),
+		Vec3::new(6.0, 0.0, 0.0),
+		Vec3::new(0.0, 2.0, 0.0),
+		Arc::new(Metal::new(Vec3::new(0.6, 0.6, 0.6), 0.3))
+	).with_alpha_mask(AlphaMask::threshold(wire, 0.5))));
+
+	// sphere with every other check cut away, the far side shows through the gaps
+	let checks = Arc::new(UVCheckerTexture::from_colors(
+		8.0,
+		4.0,
+		Vec3::new(1.0, 1.0, 1.0),
+		Vec3::ZERO
+	));
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(-1.5, 1.0, -1.5),
+		1.0,
+		Arc::new(Lambertian::from_color(Vec3::new(0.8, 0.3, 0.2)))
+	).with_alpha_mask(AlphaMask::new(checks, AlphaMode::THRESHOLD(0.5)))));
+
+	// half transparent leaf, rays pass through at random so it averages to a see-through green
+	world.add(Box::new(Triangle::new(
+		Vec3::new(0.5, 0.2, -1.0),
+		Vec3::new(3.0, 0.2, -2.0),
+		Vec3::new(1.5, 2.5, -1.5),
+		Arc::new(Lambertian::from_color(Vec3::new(0.2, 0.6, 0.1)))
+	).with_alpha_mask(AlphaMask::stochastic(Arc::new(SolidColorTexture::from_rgb(0.5, 0.5, 0.5))))));
+
+	world.add(Box::new(Quad::new(
+		Vec3::new(-2.0, 6.0, -2.0),
+		Vec3::new(4.0, 0.0, 0.0),
+		Vec3::new(0.0, 0.0, 4.0),
+		Arc::new(DiffuseLight::from_color(Vec3::new(4.0, 4.0, 4.0)))
+	)));
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		35.0,
+		Vec3::new(0.0, 2.5, 10.0),
+		Vec3::new(0.0, 1.0, -0.5),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.5, 0.6, 0.7))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn bump_maps(image_file: &mut File) -> Result<(), Box<dyn Error>> {
+
+	let mut world = HittableList::new();
+
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(0.0, -1000.0, 0.0),
+		1000.0,
+		Arc::new(Lambertian::from_color(Vec3::new(0.5, 0.5, 0.5)))
+	)));
+
+	// hammered metal, the height comes from noise
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(-1.2, 1.0, 0.0),
+		1.0,
+		Arc::new(BumpMapped::new(
+			Arc::new(Metal::new(Vec3::new(0.8, 0.6, 0.4), 0.1)),
+			Arc::new(NoiseTexture::new(8.0)),
+			0.02
+		))
+	)));
+
+	// riveted plate, a grid of domes written straight into a tangent-space normal map
+	let rivets = image::RgbImage::from_fn(256, 256, |x, y| {
+		let cell_x = (x % 32) as f64 / 16.0 - 1.0;
+		let cell_y = (y % 32) as f64 / 16.0 - 1.0;
+		let radius_sq = cell_x * cell_x + cell_y * cell_y;
+
+		// image rows run down while v runs up
+		let normal = if radius_sq < 0.36 {
+			Vec3::new(cell_x, -cell_y, f64::sqrt(0.36 - radius_sq)).unit()
+		} else {
+			Vec3::new(0.0, 0.0, 1.0)
+		};
+		let encode = |value: f64| (255.0 * (0.5 * value + 0.5)).round() as u8;
+		image::Rgb([encode(normal.x), encode(normal.y), encode(normal.z)])
+	});
+	let normal_map = Arc::new(ImageTexture::from_image(
+		image::DynamicImage::ImageRgb8(rivets),
+		ImageSettings { color_space: ColorSpace::LINEAR, ..ImageSettings::default() }
+	));
+	world.add(Box::new(Quad::new(
+		Vec3::new(0.5, 0.0, -0.5),
+		Vec3::new(2.0, 0.0, 0.5),
+		Vec3::new(0.0, 2.0, 0.0),
+		Arc::new(NormalMapped::new(
+			Arc::new(Metal::new(Vec3::new(0.7, 0.7, 0.75), 0.2)),
+			normal_map,
+			1.0
+		))
+	)));
+
+	world.add(Box::new(Quad::new(
+		Vec3::new(-2.0, 5.0, 1.0),
+		Vec3::new(4.0, 0.0, 0.0),
+		Vec3::new(0.0, 0.0, 3.0),
+		Arc::new(DiffuseLight::from_color(Vec3::new(4.0, 4.0, 4.0)))
+	)));
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		30.0,
+		Vec3::new(0.0, 2.0, 9.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.5, 0.6, 0.7))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn displaced(image_file: &mut File) -> Result<(), Box<dyn Error>> {
+
+	let mut world = HittableList::new();
+
+	// TERRAIN //
+	// ridged noise raising a flat quad into mountains
+	let mountains = Displacement {
+		texture: Arc::new(ProceduralTexture::new(
+			Pattern::RIDGED,
+			NoiseSettings { scale: 0.4, ..NoiseSettings::default() },
+			ColorRamp::grayscale()
+		)),
+		scale: 1.5,
+		subdivision_level: 6,
+		bounds_padding: 0.0
+	};
+	world.add(Box::new(mountains.quad(
+		Vec3::new(-8.0, -1.0, 4.0),
+		Vec3::new(16.0, 0.0, 0.0),
+		Vec3::new(0.0, 0.0, -16.0),
+		Arc::new(Lambertian::from_color(Vec3::new(0.5, 0.45, 0.4)))
+	)));
+
+	// ROCK //
+	// a cube whose corners share normals, so it swells into a lumpy closed rock
+	let lumps = Displacement {
+		texture: Arc::new(NoiseTexture::new(3.0)),
+		scale: 0.3,
+		subdivision_level: 4,
+		bounds_padding: 0.0
+	};
+	let corners: Vec<Vec3> = (0..8)
+		.map(|i| Vec3::new((i & 1) as f64, ((i >> 1) & 1) as f64, ((i >> 2) & 1) as f64) - Vec3::new(0.5, 0.5, 0.5))
+		.collect();
+	let sides = [
+		[0, 2, 3], [0, 3, 1], [4, 5, 7], [4, 7, 6],
+		[0, 1, 5], [0, 5, 4], [2, 6, 7], [2, 7, 3],
+		[0, 4, 6], [0, 6, 2], [1, 3, 7], [1, 7, 5]
+	];
+	world.add(Box::new(lumps.mesh(
+		&corners.iter().map(|&corner| 1.5 * corner + Vec3::new(0.0, 1.5, 0.0)).collect::<Vec<_>>(),
+		None,
+		&sides,
+		Arc::new(Lambertian::from_color(Vec3::new(0.3, 0.3, 0.35)))
+	)));
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		40.0,
+		Vec3::new(0.0, 4.0, 10.0),
+		Vec3::new(0.0, 0.5, 0.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.7, 0.8, 1.0))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn texture_sampling(image_file: &mut File, texture_cache: &mut TextureCache) -> Result<(), Box<dyn Error>> {
+
+	let mut world = HittableList::new();
+
+	// FILTERS //
+	// a small patch of the map blown up, showing texels, blended texels and smooth curves
+	let filters = [Filter::NEAREST, Filter::BILINEAR, Filter::BICUBIC];
+	for (i, filter) in filters.into_iter().enumerate() {
+		let settings = ImageSettings {
+			filter,
+			mip_filter: MipFilter::NONE,
+			uv_scale: (0.02, 0.04),
+			uv_offset: (0.5, 0.72),
+			..ImageSettings::default()
+		};
+		world.add(Box::new(Quad::new(
+			Vec3::new(-3.3 + 2.2 * i as f64, 0.1, 0.0),
+			Vec3::new(2.0, 0.0, 0.0),
+			Vec3::new(0.0, 2.0, 0.0),
+			Arc::new(Lambertian::from_texture(texture_cache.load_with_settings("earthmap.jpg", settings)?))
+		)));
+	}
+
+	// WRAP MODES //
+	// the map shrunk, turned and shifted so every quad reaches past its edges
+	let wrap_modes = [WrapMode::REPEAT, WrapMode::MIRROR, WrapMode::CLAMP];
+	for (i, wrap_mode) in wrap_modes.into_iter().enumerate() {
+		let settings = ImageSettings {
+			wrap_mode,
+			uv_scale: (2.0, 2.0),
+			uv_rotation: 15.0,
+			uv_offset: (-0.5, -0.5),
+			..ImageSettings::default()
+		};
+		world.add(Box::new(Quad::new(
+			Vec3::new(-3.3 + 2.2 * i as f64, -2.1, 0.0),
+			Vec3::new(2.0, 0.0, 0.0),
+			Vec3::new(0.0, 2.0, 0.0),
+			Arc::new(Lambertian::from_texture(texture_cache.load_with_settings("earthmap.jpg", settings)?))
+		)));
+	}
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		40.0,
+		Vec3::new(0.0, 0.0, 8.0),
+		Vec3::new(0.0, 0.0, 0.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.7, 0.8, 1.0))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn mip_filtering(image_file: &mut File, texture_cache: &mut TextureCache) -> Result<(), Box<dyn Error>> {
+
+	let mut world = HittableList::new();
+
+	// three strips of tiled map running to the horizon: aliasing, blur, sharp
+	let mip_filters = [MipFilter::NONE, MipFilter::TRILINEAR, MipFilter::EWA];
+	for (i, mip_filter) in mip_filters.into_iter().enumerate() {
+		let settings = ImageSettings {
+			mip_filter,
+			uv_scale: (2.0, 40.0),
+			..ImageSettings::default()
+		};
+		world.add(Box::new(Quad::new(
+			Vec3::new(-3.0 + 2.0 * i as f64, 0.0, 5.0),
+			Vec3::new(2.0, 0.0, 0.0),
+			Vec3::new(0.0, 0.0, -200.0),
+			Arc::new(Lambertian::from_texture(texture_cache.load_with_settings("earthmap.jpg", settings)?))
+		)));
+	}
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		40.0,
+		Vec3::new(0.0, 1.0, 6.0),
+		Vec3::new(0.0, 0.0, -20.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.7, 0.8, 1.0))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn procedural_patterns(image_file: &mut File) -> Result<(), Box<dyn Error>> {
+
+	let mut world = HittableList::new();
+
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(0.0, -1000.0, 0.0),
+		1000.0,
+		Arc::new(Lambertian::from_color(Vec3::new(0.5, 0.5, 0.5)))
+	)));
+
+	let stone = ColorRamp::new(vec![
+		(0.0, Vec3::new(0.1, 0.1, 0.12)),
+		(0.5, Vec3::new(0.5, 0.5, 0.5)),
+		(1.0, Vec3::new(0.9, 0.9, 0.85))
+	]);
+	let wood = ColorRamp::new(vec![
+		(0.0, Vec3::new(0.35, 0.18, 0.07)),
+		(1.0, Vec3::new(0.7, 0.45, 0.2))
+	]);
+	let cells = ColorRamp::new(vec![
+		(0.0, Vec3::new(0.1, 0.2, 0.6)),
+		(0.5, Vec3::new(0.2, 0.7, 0.3)),
+		(1.0, Vec3::new(0.9, 0.8, 0.1))
+	]);
+
+	// one sphere per pattern, left to right
+	let patterns = [
+		(Pattern::FBM, NoiseSettings { scale: 2.0, ..NoiseSettings::default() }, ColorRamp::grayscale()),
+		(Pattern::RIDGED, NoiseSettings { scale: 1.5, ..NoiseSettings::default() }, stone),
+		(Pattern::WORLEY, NoiseSettings { scale: 4.0, ..NoiseSettings::default() }, ColorRamp::grayscale()),
+		(Pattern::VORONOI, NoiseSettings { scale: 4.0, ..NoiseSettings::default() }, cells),
+		(Pattern::MARBLE, NoiseSettings { scale: 2.0, distortion: 6.0, ..NoiseSettings::default() }, ColorRamp::grayscale()),
+		(Pattern::WOOD, NoiseSettings { scale: 3.0, distortion: 1.0, ..NoiseSettings::default() }, wood)
+	];
+	for (i, (pattern, settings, ramp)) in patterns.into_iter().enumerate() {
+		world.add(Box::new(Sphere::new_stationary(
+			Vec3::new(-5.0 + 2.0 * i as f64, 0.8, 0.0),
+			0.8,
+			Arc::new(Lambertian::from_texture(Arc::new(ProceduralTexture::new(pattern, settings, ramp))))
+		)));
+	}
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		40.0,
+		Vec3::new(0.0, 2.0, 10.0),
+		Vec3::new(0.0, 0.8, 0.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.7, 0.8, 1.0))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn texture_composition(image_file: &mut File, texture_cache: &mut TextureCache) -> Result<(), Box<dyn Error>> {
+
+	let mut world = HittableList::new();
+
+	let earth: Arc<dyn Texture> = texture_cache.load("earthmap.jpg")?;
+	let noise: Arc<dyn Texture> = Arc::new(NoiseTexture::new(4.0));
+	let grid: Arc<dyn Texture> = Arc::new(GridTexture::from_colors(
+		12.0,
+		0.1,
+		Vec3::ZERO,
+		Vec3::new(1.0, 1.0, 1.0)
+	));
+	let checker: Arc<dyn Texture> = Arc::new(UVCheckerTexture::from_colors(
+		16.0,
+		8.0,
+		Vec3::new(0.9, 0.3, 0.2),
+		Vec3::new(0.9, 0.9, 0.9)
+	));
+	let heat = ColorRamp::new(vec![
+		(0.0, Vec3::new(0.0, 0.0, 0.3)),
+		(0.5, Vec3::new(0.8, 0.1, 0.1)),
+		(1.0, Vec3::new(1.0, 0.9, 0.3))
+	]);
+
+	// a three by three grid of spheres, one combinator each, read left to right and top to bottom
+	let textures: [Arc<dyn Texture>; 9] = [
+		// latitude and longitude lines drawn over the map
+		Arc::new(MultiplyTexture::new(earth.clone(), grid)),
+		Arc::new(AddTexture::new(checker.clone(), Arc::new(SolidColorTexture::from_rgb(0.0, 0.0, 0.3)))),
+		Arc::new(MixTexture::new(earth.clone(), checker, noise.clone())),
+		Arc::new(InvertTexture::new(earth.clone())),
+		Arc::new(ClampTexture::new(noise.clone(), 0.3, 0.7)),
+		Arc::new(RampTexture::new(noise.clone(), heat)),
+		// the oceans turned orange
+		Arc::new(HSVTexture::new(earth.clone(), 180.0, 1.5, 1.0)),
+		Arc::new(UVTransformTexture::new(earth, (3.0, 3.0), 30.0, (0.0, 0.0))),
+		// noise stretched along y
+		Arc::new(PositionTransformTexture::new(noise, Vec3::new(1.0, 0.1, 1.0), Vec3::ZERO))
+	];
+	for (i, texture) in textures.into_iter().enumerate() {
+		let (row, column) = (i / 3, i % 3);
+		world.add(Box::new(Sphere::new_stationary(
+			Vec3::new(-2.4 + 2.4 * column as f64, 2.4 - 2.4 * row as f64, 0.0),
+			1.0,
+			Arc::new(Lambertian::from_texture(texture))
+		)));
+	}
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		40.0,
+		Vec3::new(0.0, 0.0, 12.0),
+		Vec3::new(0.0, 0.0, 0.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.7, 0.8, 1.0))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn projections(image_file: &mut File, texture_cache: &mut TextureCache) -> Result<(), Box<dyn Error>> {
+
+	let mut world = HittableList::new();
+
+	let earth: Arc<dyn Texture> = texture_cache.load("earthmap.jpg")?;
+	let checker: Arc<dyn Texture> = Arc::new(UVCheckerTexture::from_colors(
+		8.0,
+		8.0,
+		Vec3::new(0.9, 0.3, 0.2),
+		Vec3::new(0.9, 0.9, 0.9)
+	));
+
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(0.0, -1000.0, 0.0),
+		1000.0,
+		Arc::new(Lambertian::from_texture(Arc::new(PlanarProjectionTexture::new(
+			checker.clone(),
+			Vec3::ZERO,
+			Vec3::new(8.0, 0.0, 0.0),
+			Vec3::new(0.0, 0.0, 8.0)
+		))))
+	)));
+
+	// a long box keeps square checks on every face, its own quads would stretch them
+	world.add(Box::new(Quad::cube(
+		Vec3::new(-4.5, 0.0, -0.5),
+		Vec3::new(-2.5, 1.0, 0.5),
+		Arc::new(Lambertian::from_texture(Arc::new(TriplanarTexture::new(checker, 1.0, 4.0))))
+	)));
+	world.add(Box::new(Cylinder::new(
+		Vec3::new(-0.8, 0.0, 0.0),
+		0.6,
+		1.6,
+		Arc::new(Lambertian::from_texture(Arc::new(CylindricalProjectionTexture::new(
+			earth.clone(),
+			Vec3::new(-0.8, 0.0, 0.0),
+			Vec3::new(0.0, 1.0, 0.0),
+			1.6
+		))))
+	)));
+	world.add(Box::new(Torus::new(
+		Vec3::new(1.2, 0.4, 0.0),
+		0.7,
+		0.35,
+		Arc::new(Lambertian::from_texture(Arc::new(SphericalProjectionTexture::new(
+			earth.clone(),
+			Vec3::new(1.2, 0.4, 0.0)
+		))))
+	)));
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(3.3, 0.8, 0.0),
+		0.8,
+		Arc::new(Lambertian::from_texture(earth))
+	)));
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		40.0,
+		Vec3::new(0.0, 4.0, 10.0),
+		Vec3::new(0.0, 0.6, 0.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.7, 0.8, 1.0))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn voxel_smoke(image_file: &mut File) -> Result<(), Box<dyn Error>> {
+	// the struct, not the module of the same name declared above
+	use crate::AABB::AABB;
+
+	let mut world = HittableList::new();
+
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(0.0, -1000.0, 0.0),
+		1000.0,
+		Arc::new(Lambertian::from_color(Vec3::new(0.4, 0.4, 0.4)))
+	)));
+
+	// SMOKE //
+	// a 64^3 grid of bytes from a simulation or sculpting tool, baked fbm noise when there is none
+	let smoke_bounds = AABB::from_corners(Vec3::new(-1.5, 0.0, -1.5), Vec3::new(1.5, 3.0, 1.5));
+	let smoke_grid = match VoxelGrid::load_raw("smoke.raw", VoxelFormat::U8, 1, (64, 64, 64), smoke_bounds) {
+		Ok(grid) => grid,
+		Err(error) => {
+			info!("Could not load smoke.raw ({}), baking noise instead", error);
+			let smoke_noise = ProceduralTexture::new(
+				Pattern::FBM,
+				NoiseSettings { scale: 1.2, ..NoiseSettings::default() },
+				ColorRamp::new(vec![(0.55, Vec3::ZERO), (0.8, Vec3::new(1.0, 1.0, 1.0))])
+			);
+			VoxelGrid::from_texture((64, 64, 64), smoke_bounds, &smoke_noise)
+		}
+	};
+	let smoke_density = VoxelTexture::with_scale(Arc::new(smoke_grid), 6.0);
+	let bounds = smoke_density.grid().bounds();
+	let majorant = smoke_density.max_scalar();
+
+	// the colour field is baked the same way, marble veins tinting the smoke
+	let smoke_color = ProceduralTexture::new(
+		Pattern::MARBLE,
+		NoiseSettings::default(),
+		ColorRamp::new(vec![(0.0, Vec3::new(0.9, 0.4, 0.2)), (1.0, Vec3::new(0.3, 0.5, 0.9))])
+	);
+	let color_grid = VoxelGrid::from_texture((32, 32, 32), bounds, &smoke_color);
+
+	world.add(Box::new(HeterogeneousMedium::new(
+		Box::new(Quad::cube(
+			Vec3::new(bounds.x.min, bounds.y.min, bounds.z.min),
+			Vec3::new(bounds.x.max, bounds.y.max, bounds.z.max),
+			Arc::new(Lambertian::from_color(Vec3::ZERO))
+		)),
+		Arc::new(smoke_density),
+		majorant,
+		Arc::new(Isotropic::new(Arc::new(VoxelTexture::new(Arc::new(color_grid)))))
+	)));
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		40.0,
+		Vec3::new(0.0, 3.0, 10.0),
+		Vec3::new(0.0, 1.5, 0.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.7, 0.8, 1.0))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn model(image_file: &mut File, texture_cache: &mut TextureCache, filepath: &str) -> Result<(), Box<dyn Error>> {
+
+	let default_material: Arc<dyn Material> = Arc::new(Lambertian::from_color(Vec3::new(0.7, 0.7, 0.7)));
+
+	let extension = Path::new(filepath).extension()
+		.and_then(|extension| extension.to_str())
+		.unwrap_or("")
+		.to_ascii_lowercase();
+	// only glTF files bring their own camera
+	let mut file_camera = None;
+	let model = match extension.as_str() {
+		"obj" => obj::load(filepath, default_material, texture_cache)?,
+		"gltf" | "glb" => {
+			let scene = gltf::load(filepath, default_material)?;
+			file_camera = scene.camera;
+			scene.objects
+		}
+		"ply" | "stl" => {
+			let data = if extension == "ply" { ply::load(filepath)? } else { stl::load(filepath)? };
+			// only a VertexColorTexture shows the colors stored with the vertices
+			let material: Arc<dyn Material> = match data.colors {
+				Some(_) => Arc::new(Lambertian::from_texture(Arc::new(VertexColorTexture::new(Vec3::new(0.7, 0.7, 0.7))))),
+				None => default_material
+			};
+			let mesh = data.mesh(material);
+			info!("{} faces in {} KiB of vertex buffers", mesh.face_count(), mesh.memory_usage() / 1024);
+			let mut model = HittableList::new();
+			model.add(Box::new(mesh));
+			model
+		}
+		_ => return Err(format!("no loader for '{}'", filepath).into())
+	};
+	info!("Loaded {} objects from {}", model.objects.len(), filepath);
+
+	// framed whatever its size, resting on the ground
+	let bbox = model.bbox;
+	let center = Vec3::new(
+		(bbox.x.min + bbox.x.max) / 2.0,
+		(bbox.y.min + bbox.y.max) / 2.0,
+		(bbox.z.min + bbox.z.max) / 2.0
+	);
+	let radius = 0.5 * Vec3::new(bbox.x.size(), bbox.y.size(), bbox.z.size()).length();
+
+	let mut world = HittableList::new();
+
+	world.add(Box::new(Sphere::new_stationary(
+		Vec3::new(center.x, bbox.y.min - 1000.0 * radius, center.z),
+		1000.0 * radius,
+		Arc::new(Lambertian::from_color(Vec3::new(0.4, 0.4, 0.4)))
+	)));
+	world.add(BVHNode::from_list(model));
+
+	let sample_settings = SampleSettings {
+		confidence: 0.95, // 95% confidence => 1.96
+		tolerance: 0.25,
+		batch_size: 32,
+		max_samples: 1000
+	};
+	let background = Background::SOLID(Vec3::new(0.7, 0.8, 1.0));
+
+	let camera = match file_camera {
+		Some(file_camera) => file_camera.to_camera(16.0 / 9.0, 400, sample_settings, 50, background),
+		None => Camera::new(
+			16.0 / 9.0,
+			400,
+			sample_settings,
+			50,
+			40.0,
+			center + Vec3::new(0.0, 0.4, 1.0).unit() * 3.0 * radius,
+			center,
+			Vec3::new(0.0, 1.0, 0.0),
+			0.0,
+			10.0,
+			background
+		)
+	};
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
+
+fn culling(image_file: &mut File) -> Result<(), Box<dyn Error>> {
+
+	let mut world = HittableList::new();
+
+	// backdrop showing through culled panels
+	world.add(Box::new(Quad::new(
+		Vec3::new(-6.0, -4.0, -2.0),
+		Vec3::new(12.0, 0.0, 0.0),
+		Vec3::new(0.0, 8.0, 0.0),
+		Arc::new(Lambertian::from_texture(Arc::new(CheckeredTexture::from_colors(
+			0.5,
+			Vec3::new(0.2, 0.2, 0.2),
+			Vec3::new(0.9, 0.9, 0.9)
+		))))
+	)));
+
+	// columns: no culling, back faces culled, front faces culled
+	// top row faces the camera, bottom row faces away from it
+	let panel_material: Arc<dyn Material> = Arc::new(Lambertian::from_color(Vec3::new(0.9, 0.5, 0.1)));
+	for (column, mode) in [Culling::NONE, Culling::BACK, Culling::FRONT].into_iter().enumerate() {
+		for (row, facing_camera) in [true, false].into_iter().enumerate() {
+			let center = Vec3::new(-2.5 + 2.5 * column as f64, 1.2 - 2.4 * row as f64, 0.0);
+			let [lower_left, lower_right, upper_right, upper_left] = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+				.map(|(x, y)| center + Vec3::new(x, y, 0.0));
+
+			// counter-clockwise seen from the front
+			let corners = if facing_camera {
+				[[lower_left, lower_right, upper_right], [lower_left, upper_right, upper_left]]
+			} else {
+				[[lower_left, upper_right, lower_right], [lower_left, upper_left, upper_right]]
+			};
+			for [a, b, c] in corners {
+				world.add(Box::new(Triangle::new(a, b, c, panel_material.clone()).with_culling(mode)));
+			}
+		}
+	}
+
+	let camera = Camera::new(
+		16.0 / 9.0,
+		400,
+		SampleSettings {
+			confidence: 0.95, // 95% confidence => 1.96
+			tolerance: 0.25,
+			batch_size: 32,
+			max_samples: 1000
+		},
+		50,
+		40.0,
+		Vec3::new(0.0, 0.0, 10.0),
+		Vec3::new(0.0, 0.0, 0.0),
+		Vec3::new(0.0, 1.0, 0.0),
+		0.0,
+		10.0,
+		Background::SOLID(Vec3::new(0.7, 0.8, 1.0))
+	);
+
+	let world_bvh = BVHNode::from_list(world);
+	camera.render(world_bvh, image_file)?;
+
+	Ok(())
+}
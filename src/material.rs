@@ -4,7 +4,8 @@ use std::sync::Arc;
 use crate::hittable::hittable::HitRecord;
 use crate::ONB::OrthonormalBasis;
 use crate::ray::Ray;
-use crate::texture::{SolidColorTexture, Texture};
+use crate::texture::image_texture::ImageTexture;
+use crate::texture::texture::{SolidColorTexture, Texture};
 use crate::util::util::{random_cosine_direction, random_unit_vector};
 use crate::util::vec3::Vec3;
 
@@ -15,7 +16,7 @@ pub trait Material: Send + Sync {
 		hit_record: &HitRecord
 	) -> Option<ScatterRecord> { None }
 
-	fn emitted(&self, u: f64, v: f64, p: Vec3) -> Vec3 { Vec3::ZERO }
+	fn emitted(&self, u: f64, v: f64, p: Vec3, _normal: Vec3) -> Vec3 { Vec3::ZERO }
 	
 	fn scattering_pdf(&self, ray_in: Ray, hit_record: &HitRecord, scattered: Ray) -> f64 { 0.0 }
 }
@@ -43,14 +44,17 @@ impl Lambertian {
 
 impl Material for Lambertian {
 	fn scatter(&self, ray_in: Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
-		let attenuation = self.texture.value_at(
+		let attenuation = self.texture.filtered_value_at(
 			hit_record.u,
 			hit_record.v,
-			hit_record.position
+			hit_record.position,
+			hit_record.normal,
+			hit_record.uv_differentials
 		);
 
 		let basis = OrthonormalBasis::new(hit_record.normal);
 		let scatter_direction = basis.transform(random_cosine_direction()).unit();
+		if !hit_record.is_on_incident_side(scatter_direction) { return None; }
 
 		let scattered_ray = Ray::new(
 			hit_record.position,
@@ -74,12 +78,17 @@ impl Material for Lambertian {
 
 pub struct Metal {
 	albedo: Vec3,
-	fuzz: f64
+	fuzz: f64,
+	thin_film: Option<ThinFilm>
 }
 
 impl Metal {
 	pub fn new(albedo: Vec3, fuzz: f64) -> Self {
-		Metal { albedo, fuzz }
+		Metal { albedo, fuzz, thin_film: None }
+	}
+
+	pub fn with_thin_film(albedo: Vec3, fuzz: f64, thin_film: ThinFilm) -> Self {
+		Metal { albedo, fuzz, thin_film: Some(thin_film) }
 	}
 }
 
@@ -89,9 +98,17 @@ impl Material for Metal {
 		reflected = reflected.unit() + self.fuzz * random_unit_vector();
 
 		let scattered_ray = Ray::new(hit_record.position, reflected, ray_in.time);
-		if scattered_ray.direction.dot(hit_record.normal) > 0.0 {
+		if hit_record.is_on_incident_side(scattered_ray.direction) {
+			let attenuation = match &self.thin_film {
+				Some(film) => {
+					let cos_theta = f64::min((-ray_in.direction.unit()).dot(hit_record.normal), 1.0);
+					film.conductor_reflectance(cos_theta, self.albedo)
+				}
+				None => self.albedo
+			};
+
 			return Some(ScatterRecord {
-				attenuation: self.albedo,
+				attenuation,
 				scattered_ray,
 				pdf: 0.0
 			});
@@ -103,12 +120,17 @@ impl Material for Metal {
 }
 
 pub struct Dielectric {
-	refraction_index: f64
+	refraction_index: f64,
+	thin_film: Option<ThinFilm>
 }
 
 impl Dielectric {
 	pub fn new(refraction_index: f64) -> Self {
-		Dielectric { refraction_index }
+		Dielectric { refraction_index, thin_film: None }
+	}
+
+	pub fn with_thin_film(refraction_index: f64, thin_film: ThinFilm) -> Self {
+		Dielectric { refraction_index, thin_film: Some(thin_film) }
 	}
 
 	pub fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
@@ -132,13 +154,38 @@ impl Material for Dielectric {
 		let sin_theta = f64::sqrt(1.0 - cos_theta * cos_theta);
 
 		let cannot_refract = ri * sin_theta > 1.0;
-		let fresnel_reflection = Self::reflectance(cos_theta, ri) > fastrand::f64();
-		let direction = if cannot_refract || fresnel_reflection {
+
+		// the film sits on the outside of the surface, so rays leaving the object skip it
+		let film = self.thin_film.as_ref().filter(|_| hit_record.is_front_face);
+		if let Some(film) = film {
+			let reflectance = film.dielectric_reflectance(cos_theta, 1.0, self.refraction_index);
+			let reflect_probability = (reflectance.x + reflectance.y + reflectance.z) / 3.0;
+
+			let reflected = cannot_refract || reflect_probability > fastrand::f64();
+			let (direction, attenuation) = if reflected {
+				(unit_direction.reflect(hit_record.normal), reflectance / reflect_probability)
+			} else {
+				let transmittance = Vec3::new(1.0, 1.0, 1.0) - reflectance;
+				(unit_direction.refract(hit_record.normal, ri), transmittance / (1.0 - reflect_probability))
+			};
+			if hit_record.is_on_incident_side(direction) != reflected { return None; }
+
+			return Some(ScatterRecord {
+				attenuation,
+				scattered_ray: Ray::new(hit_record.position, direction, ray_in.time),
+				pdf: 0.0
+			});
+		}
+
+		let reflected = cannot_refract || Self::reflectance(cos_theta, ri) > fastrand::f64();
+		let direction = if reflected {
 			unit_direction.reflect(hit_record.normal)
 		} else {
 			unit_direction.refract(hit_record.normal, ri)
 		};
 
+		// a shading normal may send the ray to the wrong side of the true surface
+		if hit_record.is_on_incident_side(direction) != reflected { return None; }
 
 		Some(ScatterRecord {
 			attenuation: Vec3::new(1.0, 1.0, 1.0),
@@ -148,6 +195,304 @@ impl Material for Dielectric {
 	}
 }
 
+// Infinitely thin dielectric sheet, e.g. a window pane modelled as a single quad.
+// Light is either reflected or passes straight through, since the refraction at the
+// back face cancels the refraction at the front face.
+pub struct ThinDielectric {
+	refraction_index: f64,
+	thin_film: Option<ThinFilm>
+}
+
+impl ThinDielectric {
+	pub fn new(refraction_index: f64) -> Self {
+		ThinDielectric { refraction_index, thin_film: None }
+	}
+
+	pub fn with_thin_film(refraction_index: f64, thin_film: ThinFilm) -> Self {
+		ThinDielectric { refraction_index, thin_film: Some(thin_film) }
+	}
+
+	// Sums the light bouncing back and forth between both faces of the sheet
+	fn slab_reflectance(r: f64) -> f64 {
+		if r < 1.0 { 2.0 * r / (1.0 + r) } else { 1.0 }
+	}
+}
+
+impl Material for ThinDielectric {
+	fn scatter(&self, ray_in: Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+		let unit_direction = ray_in.direction.unit();
+		let cos_theta = f64::min((-unit_direction).dot(hit_record.normal), 1.0);
+
+		let surface_reflectance = match &self.thin_film {
+			Some(film) => film.dielectric_reflectance(cos_theta, 1.0, self.refraction_index),
+			None => {
+				let r = fresnel_dielectric(cos_theta, 1.0, self.refraction_index);
+				Vec3::new(r, r, r)
+			}
+		};
+
+		let reflectance = Vec3::new(
+			Self::slab_reflectance(surface_reflectance.x),
+			Self::slab_reflectance(surface_reflectance.y),
+			Self::slab_reflectance(surface_reflectance.z)
+		);
+		let reflect_probability = (reflectance.x + reflectance.y + reflectance.z) / 3.0;
+
+		let reflected = reflect_probability > fastrand::f64();
+		let (direction, attenuation) = if reflected {
+			(unit_direction.reflect(hit_record.normal), reflectance / reflect_probability)
+		} else {
+			let transmittance = Vec3::new(1.0, 1.0, 1.0) - reflectance;
+			(unit_direction, transmittance / (1.0 - reflect_probability))
+		};
+		if hit_record.is_on_incident_side(direction) != reflected { return None; }
+
+		Some(ScatterRecord {
+			attenuation,
+			scattered_ray: Ray::new(hit_record.position, direction, ray_in.time),
+			pdf: 0.0
+		})
+	}
+}
+
+// Thin coating layer (soap film, lens coating, oxide layer) producing interference colors.
+// Thickness is in nanometers.
+#[derive(Copy, Clone)]
+pub struct ThinFilm {
+	pub thickness: f64,
+	pub refraction_index: f64
+}
+
+impl ThinFilm {
+	// Representative wavelengths (nm) of the red, green and blue channels
+	const WAVELENGTHS: [f64; 3] = [650.0, 510.0, 475.0];
+
+	pub fn new(thickness: f64, refraction_index: f64) -> Self {
+		ThinFilm { thickness, refraction_index }
+	}
+
+	// Reflectance of the film on top of a dielectric substrate, per color channel
+	pub fn dielectric_reflectance(&self, cos_theta: f64, outside_index: f64, substrate_index: f64) -> Vec3 {
+		let n1 = outside_index;
+		let n2 = self.refraction_index;
+		let n3 = substrate_index;
+
+		// refraction into the film
+		let sin_theta_1_sq = 1.0 - cos_theta * cos_theta;
+		let sin_theta_2_sq = (n1 / n2) * (n1 / n2) * sin_theta_1_sq;
+		if sin_theta_2_sq >= 1.0 { return Vec3::new(1.0, 1.0, 1.0); }
+		let cos_theta_2 = f64::sqrt(1.0 - sin_theta_2_sq);
+
+		// refraction into the substrate, total internal reflection reflects everything at the base
+		let sin_theta_3_sq = (n1 / n3) * (n1 / n3) * sin_theta_1_sq;
+		let (r23_s, r23_p) = if sin_theta_3_sq >= 1.0 {
+			(1.0, 1.0)
+		} else {
+			let cos_theta_3 = f64::sqrt(1.0 - sin_theta_3_sq);
+			(
+				(n2 * cos_theta_2 - n3 * cos_theta_3) / (n2 * cos_theta_2 + n3 * cos_theta_3),
+				(n3 * cos_theta_2 - n2 * cos_theta_3) / (n3 * cos_theta_2 + n2 * cos_theta_3)
+			)
+		};
+
+		let r12_s = (n1 * cos_theta - n2 * cos_theta_2) / (n1 * cos_theta + n2 * cos_theta_2);
+		let r12_p = (n2 * cos_theta - n1 * cos_theta_2) / (n2 * cos_theta + n1 * cos_theta_2);
+
+		let channel = |wavelength: f64| {
+			let phase = self.phase_difference(cos_theta_2, wavelength);
+			0.5 * (Self::airy_reflectance(r12_s, r23_s, phase) + Self::airy_reflectance(r12_p, r23_p, phase))
+		};
+
+		Vec3::new(
+			channel(Self::WAVELENGTHS[0]),
+			channel(Self::WAVELENGTHS[1]),
+			channel(Self::WAVELENGTHS[2])
+		)
+	}
+
+	// Reflectance of the film on top of a conductor, per color channel.
+	// The conductor is approximated by a real amplitude coefficient derived from its albedo.
+	pub fn conductor_reflectance(&self, cos_theta: f64, albedo: Vec3) -> Vec3 {
+		let n1 = 1.0;
+		let n2 = self.refraction_index;
+
+		let sin_theta_2_sq = (n1 / n2) * (n1 / n2) * (1.0 - cos_theta * cos_theta);
+		let cos_theta_2 = f64::sqrt(1.0 - sin_theta_2_sq);
+
+		let r12_s = (n1 * cos_theta - n2 * cos_theta_2) / (n1 * cos_theta + n2 * cos_theta_2);
+		let r12_p = (n2 * cos_theta - n1 * cos_theta_2) / (n2 * cos_theta + n1 * cos_theta_2);
+
+		let channel = |wavelength: f64, substrate_reflectance: f64| {
+			// reflection off a conductor flips the phase
+			let r23 = -f64::sqrt(substrate_reflectance.clamp(0.0, 1.0));
+			let phase = self.phase_difference(cos_theta_2, wavelength);
+			0.5 * (Self::airy_reflectance(r12_s, r23, phase) + Self::airy_reflectance(r12_p, r23, phase))
+		};
+
+		Vec3::new(
+			channel(Self::WAVELENGTHS[0], albedo.x),
+			channel(Self::WAVELENGTHS[1], albedo.y),
+			channel(Self::WAVELENGTHS[2], albedo.z)
+		)
+	}
+
+	fn phase_difference(&self, cos_theta_film: f64, wavelength: f64) -> f64 {
+		4.0 * PI * self.refraction_index * self.thickness * cos_theta_film / wavelength
+	}
+
+	// Sum of all reflections inside the film (Airy formula) for real amplitude coefficients
+	fn airy_reflectance(r12: f64, r23: f64, phase: f64) -> f64 {
+		let cross = 2.0 * r12 * r23 * phase.cos();
+		let reflectance = (r12 * r12 + r23 * r23 + cross) / (1.0 + r12 * r12 * r23 * r23 + cross);
+		reflectance.clamp(0.0, 1.0)
+	}
+}
+
+// Exact unpolarized Fresnel reflectance at a dielectric interface
+pub fn fresnel_dielectric(cos_theta: f64, outside_index: f64, inside_index: f64) -> f64 {
+	let sin_theta_t_sq = (outside_index / inside_index).powi(2) * (1.0 - cos_theta * cos_theta);
+	if sin_theta_t_sq >= 1.0 { return 1.0; }
+	let cos_theta_t = f64::sqrt(1.0 - sin_theta_t_sq);
+
+	let r_s = (outside_index * cos_theta - inside_index * cos_theta_t)
+		/ (outside_index * cos_theta + inside_index * cos_theta_t);
+	let r_p = (inside_index * cos_theta - outside_index * cos_theta_t)
+		/ (inside_index * cos_theta + outside_index * cos_theta_t);
+
+	0.5 * (r_s * r_s + r_p * r_p)
+}
+
+// Fabric: diffuse base with a Charlie sheen lobe for the soft grazing highlights of velvet and cloth
+pub struct Cloth {
+	base_texture: Arc<dyn Texture>,
+	sheen_texture: Arc<dyn Texture>,
+	roughness: f64,
+	// fraction of light the sheen lobe reflects, over the cosine of the view angle
+	sheen_albedo: [f64; Self::ALBEDO_SIZE]
+}
+
+impl Cloth {
+	const ALBEDO_SIZE: usize = 64;
+
+	pub fn new(base_texture: Arc<dyn Texture>, sheen_texture: Arc<dyn Texture>, roughness: f64) -> Self {
+		let mut cloth = Cloth {
+			base_texture,
+			sheen_texture,
+			roughness: roughness.clamp(0.01, 1.0),
+			sheen_albedo: [0.0; Self::ALBEDO_SIZE]
+		};
+		cloth.sheen_albedo = std::array::from_fn(|i| cloth.integrate_sheen(i as f64 / (Self::ALBEDO_SIZE - 1) as f64));
+
+		cloth
+	}
+
+	pub fn from_colors(base_color: Vec3, sheen_color: Vec3, roughness: f64) -> Self {
+		Cloth::new(
+			Arc::new(SolidColorTexture::new(base_color)),
+			Arc::new(SolidColorTexture::new(sheen_color)),
+			roughness
+		)
+	}
+
+	// Charlie sheen distribution (Estevez & Kulla)
+	fn charlie_distribution(&self, cos_theta_h: f64) -> f64 {
+		let alpha = self.roughness * self.roughness;
+		let inv_alpha = 1.0 / alpha;
+		let sin_theta_h = f64::sqrt(f64::max(0.0, 1.0 - cos_theta_h * cos_theta_h));
+		(2.0 + inv_alpha) * sin_theta_h.powf(inv_alpha) / (2.0 * PI)
+	}
+
+	// Ashikhmin/Neubelt visibility term
+	fn visibility(cos_theta_in: f64, cos_theta_out: f64) -> f64 {
+		1.0 / (4.0 * (cos_theta_in + cos_theta_out - cos_theta_in * cos_theta_out))
+	}
+
+	fn sheen_lobe(&self, cos_theta_in: f64, cos_theta_out: f64, cos_theta_h: f64) -> f64 {
+		self.charlie_distribution(cos_theta_h) * Self::visibility(cos_theta_in, cos_theta_out)
+	}
+
+	// Integral of the sheen lobe times the cosine over the hemisphere, by the midpoint rule
+	fn integrate_sheen(&self, cos_theta_out: f64) -> f64 {
+		const STEPS: usize = 64;
+		let cos_theta_out = cos_theta_out.max(1e-4);
+		let wo = Vec3::new(f64::sqrt(1.0 - cos_theta_out * cos_theta_out), 0.0, cos_theta_out);
+
+		let mut sum = 0.0;
+		for i in 0..STEPS {
+			let cos_theta_in = (i as f64 + 0.5) / STEPS as f64;
+			let sin_theta_in = f64::sqrt(1.0 - cos_theta_in * cos_theta_in);
+			for j in 0..STEPS {
+				let phi = 2.0 * PI * (j as f64 + 0.5) / STEPS as f64;
+				let wi = Vec3::new(sin_theta_in * phi.cos(), sin_theta_in * phi.sin(), cos_theta_in);
+				sum += self.sheen_lobe(cos_theta_in, cos_theta_out, (wo + wi).unit().z) * cos_theta_in;
+			}
+		}
+
+		sum * 2.0 * PI / (STEPS * STEPS) as f64
+	}
+
+	fn sheen_albedo(&self, cos_theta_out: f64) -> f64 {
+		let x = cos_theta_out.clamp(0.0, 1.0) * (Self::ALBEDO_SIZE - 1) as f64;
+		let i = usize::min(x as usize, Self::ALBEDO_SIZE - 2);
+		let fraction = x - i as f64;
+		(1.0 - fraction) * self.sheen_albedo[i] + fraction * self.sheen_albedo[i + 1]
+	}
+
+	// BRDF value for view direction `wo` and light direction `wi`, both pointing away from the surface.
+	// The Neubelt visibility reflects more than comes in at grazing angles, so the sheen lobe is
+	// normalized there, and the base only gets the light the sheen lets through.
+	fn brdf(&self, hit_record: &HitRecord, wo: Vec3, wi: Vec3) -> Vec3 {
+		let cos_theta_out = hit_record.normal.dot(wo);
+		let cos_theta_in = hit_record.normal.dot(wi);
+		if cos_theta_out <= 0.0 || cos_theta_in <= 0.0 { return Vec3::ZERO; }
+
+		let half = (wo + wi).unit();
+		let cos_theta_h = hit_record.normal.dot(half);
+
+		let (u, v, p) = (hit_record.u, hit_record.v, hit_record.position);
+		let base = self.base_texture.filtered_value_at(u, v, p, hit_record.normal, hit_record.uv_differentials);
+		let sheen = self.sheen_texture.filtered_value_at(u, v, p, hit_record.normal, hit_record.uv_differentials);
+
+		let sheen_albedo = self.sheen_albedo(cos_theta_out);
+		let max_sheen = sheen.x.max(sheen.y).max(sheen.z).clamp(0.0, 1.0);
+		let base_scale = 1.0 - max_sheen * sheen_albedo.min(1.0);
+
+		base * base_scale / PI + sheen * self.sheen_lobe(cos_theta_in, cos_theta_out, cos_theta_h) / sheen_albedo.max(1.0)
+	}
+}
+
+impl Material for Cloth {
+	fn scatter(&self, ray_in: Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+		let basis = OrthonormalBasis::new(hit_record.normal);
+		let scatter_direction = basis.transform(random_cosine_direction()).unit();
+		if !hit_record.is_on_incident_side(scatter_direction) { return None; }
+
+		let scattered_ray = Ray::new(
+			hit_record.position,
+			scatter_direction,
+			ray_in.time
+		);
+
+		let pdf = self.scattering_pdf(ray_in, hit_record, scattered_ray);
+		if pdf <= 0.0 { return None; }
+
+		// brdf * cos / pdf, which reduces to brdf * PI for cosine weighted sampling
+		let wo = -ray_in.direction.unit();
+		let attenuation = self.brdf(hit_record, wo, scatter_direction) * PI;
+
+		Some(ScatterRecord {
+			attenuation,
+			scattered_ray,
+			pdf
+		})
+	}
+
+	fn scattering_pdf(&self, _ray_in: Ray, hit_record: &HitRecord, scattered: Ray) -> f64 {
+		let cosine = hit_record.normal.dot(scattered.direction.unit());
+		f64::max(cosine, 0.0) / PI
+	}
+}
+
 pub struct DiffuseLight {
 	texture: Arc<dyn Texture>
 }
@@ -163,32 +508,55 @@ impl DiffuseLight {
 }
 
 impl Material for DiffuseLight {
-	fn emitted(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
-		self.texture.value_at(u, v, p)
+	fn emitted(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		self.texture.value_at(u, v, p, normal)
 	}
 }
 
 pub struct Isotropic {
-	texture: Arc<dyn Texture>
+	texture: Arc<dyn Texture>,
+	// radiance given off by the absorbing part of the medium, for glowing media like fire
+	emission: Option<Arc<dyn Texture>>
 }
 
 impl Isotropic {
 	pub fn new(texture: Arc<dyn Texture>) -> Self {
-		Isotropic { texture }
+		Isotropic { texture, emission: None }
 	}
 
 	pub fn from_color(color: Vec3) -> Self {
-		Isotropic { texture: Arc::new(SolidColorTexture::new(color) )}
+		Isotropic { texture: Arc::new(SolidColorTexture::new(color) ), emission: None }
+	}
+
+	pub fn with_emission(texture: Arc<dyn Texture>, emission: Arc<dyn Texture>) -> Self {
+		Isotropic { texture, emission: Some(emission) }
 	}
 }
 
 impl Material for Isotropic {
+	// Medium hits are collisions with the whole extinction, of which the complement of the
+	// albedo is absorption. Weighting by it keeps the glow independent of how collisions are sampled.
+	fn emitted(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		let Some(emission) = &self.emission else { return Vec3::ZERO; };
+
+		let albedo = self.texture.value_at(u, v, p, normal);
+		let absorption = Vec3::new(
+			(1.0 - albedo.x).clamp(0.0, 1.0),
+			(1.0 - albedo.y).clamp(0.0, 1.0),
+			(1.0 - albedo.z).clamp(0.0, 1.0)
+		);
+
+		absorption * emission.value_at(u, v, p, normal)
+	}
+
 	fn scatter(&self, ray_in: Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
 		Some(ScatterRecord {
-			attenuation: self.texture.value_at(
+			attenuation: self.texture.filtered_value_at(
 				hit_record.u,
 				hit_record.v,
-				hit_record.position
+				hit_record.position,
+				hit_record.normal,
+				hit_record.uv_differentials
 			),
 			scattered_ray: Ray::new(hit_record.position, random_unit_vector(), ray_in.time),
 			pdf: 1.0 / (4.0 * PI)
@@ -198,4 +566,204 @@ impl Material for Isotropic {
 	fn scattering_pdf(&self, ray_in: Ray, hit_record: &HitRecord, scattered: Ray) -> f64 {
 		1.0 / (4.0 * PI)
 	}
-}
\ No newline at end of file
+}
+
+// Perturbs the shading normal with a height texture before handing the hit to the wrapped material
+pub struct BumpMapped {
+	material: Arc<dyn Material>,
+	height_texture: Arc<dyn Texture>,
+	strength: f64
+}
+
+impl BumpMapped {
+	// Step in (u, v) used for finite differences of the height texture
+	const DELTA: f64 = 0.0005;
+
+	pub fn new(material: Arc<dyn Material>, height_texture: Arc<dyn Texture>, strength: f64) -> Self {
+		BumpMapped { material, height_texture, strength }
+	}
+
+	fn height(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> f64 {
+		let value = self.height_texture.value_at(u, v, p, normal);
+		self.strength * (value.x + value.y + value.z) / 3.0
+	}
+
+	fn perturb(&self, hit_record: &HitRecord) -> HitRecord {
+		let (u, v, p, n) = (hit_record.u, hit_record.v, hit_record.position, hit_record.normal);
+		let du = Self::DELTA;
+		let dv = Self::DELTA;
+
+		let displacement = self.height(u, v, p, n);
+		let u_displacement = self.height(u + du, v, p + du * hit_record.dpdu, n);
+		let v_displacement = self.height(u, v + dv, p + dv * hit_record.dpdv, n);
+
+		let dpdu = hit_record.dpdu + (u_displacement - displacement) / du * hit_record.normal;
+		let dpdv = hit_record.dpdv + (v_displacement - displacement) / dv * hit_record.normal;
+
+		hit_record.clone().with_shading_normal(dpdu.cross(dpdv))
+	}
+}
+
+impl Material for BumpMapped {
+	fn scatter(&self, ray_in: Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+		self.material.scatter(ray_in, &self.perturb(hit_record))
+	}
+
+	fn emitted(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		self.material.emitted(u, v, p, normal)
+	}
+
+	fn scattering_pdf(&self, ray_in: Ray, hit_record: &HitRecord, scattered: Ray) -> f64 {
+		self.material.scattering_pdf(ray_in, &self.perturb(hit_record), scattered)
+	}
+}
+
+// Replaces the shading normal with one read from a tangent-space normal map.
+// The map should be loaded with ColorSpace::LINEAR so the stored vectors are not gamma decoded.
+pub struct NormalMapped {
+	material: Arc<dyn Material>,
+	normal_map: Arc<ImageTexture>,
+	strength: f64
+}
+
+impl NormalMapped {
+	pub fn new(material: Arc<dyn Material>, normal_map: Arc<ImageTexture>, strength: f64) -> Self {
+		NormalMapped { material, normal_map, strength }
+	}
+
+	fn perturb(&self, hit_record: &HitRecord) -> HitRecord {
+		// texels store the tangent space normal remapped from [-1, 1] to [0, 1]
+		let texel = self.normal_map.value_at(hit_record.u, hit_record.v, hit_record.position, hit_record.normal);
+		let mut tangent_normal = 2.0 * texel - Vec3::new(1.0, 1.0, 1.0);
+		tangent_normal.x *= self.strength;
+		tangent_normal.y *= self.strength;
+
+		let normal = hit_record.normal;
+		let mut tangent = hit_record.dpdu - hit_record.dpdu.dot(normal) * normal;
+		if tangent.is_near_zero() {
+			tangent = OrthonormalBasis::new(normal).u;
+		}
+		let tangent = tangent.unit();
+
+		let mut bitangent = normal.cross(tangent);
+		if bitangent.dot(hit_record.dpdv) < 0.0 {
+			bitangent = -bitangent;
+		}
+
+		let shading_normal = tangent_normal.x * tangent
+			+ tangent_normal.y * bitangent
+			+ tangent_normal.z * normal;
+
+		hit_record.clone().with_shading_normal(shading_normal)
+	}
+}
+
+impl Material for NormalMapped {
+	fn scatter(&self, ray_in: Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+		self.material.scatter(ray_in, &self.perturb(hit_record))
+	}
+
+	fn emitted(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		self.material.emitted(u, v, p, normal)
+	}
+
+	fn scattering_pdf(&self, ray_in: Ray, hit_record: &HitRecord, scattered: Ray) -> f64 {
+		self.material.scattering_pdf(ray_in, &self.perturb(hit_record), scattered)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::hittable::hittable::Hittable;
+	use crate::hittable::quad::Quad;
+	use crate::util::interval::Interval;
+
+	// Hit on the xy plane at the origin facing +z
+	fn hit_record(material: Arc<dyn Material>) -> HitRecord {
+		let ray = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+		HitRecord::new(ray, 1.0, Vec3::ZERO, Vec3::new(0.0, 0.0, 1.0), material, 0.5, 0.5)
+	}
+
+	#[test]
+	fn thin_film_vanishes_at_zero_thickness() {
+		let film = ThinFilm::new(0.0, 1.33);
+		for cos_theta in [1.0, 0.7, 0.3, 0.05] {
+			let reflectance = film.dielectric_reflectance(cos_theta, 1.0, 1.5);
+			let bare = fresnel_dielectric(cos_theta, 1.0, 1.5);
+			for channel in [reflectance.x, reflectance.y, reflectance.z] {
+				assert!((channel - bare).abs() < 1e-12);
+			}
+		}
+	}
+
+	#[test]
+	fn quarter_wave_film_cancels_its_wavelength() {
+		// an index of sqrt(n_substrate) balances the two reflections, a quarter wave cancels them
+		let index = f64::sqrt(1.5);
+		let film = ThinFilm::new(ThinFilm::WAVELENGTHS[0] / (4.0 * index), index);
+		let reflectance = film.dielectric_reflectance(1.0, 1.0, 1.5);
+
+		assert!(reflectance.x < 1e-12);
+		assert!(reflectance.y > 1e-3 && reflectance.z > 1e-3);
+		assert!(reflectance.y < fresnel_dielectric(1.0, 1.0, 1.5));
+	}
+
+	#[test]
+	fn cloth_reflects_at_most_what_comes_in() {
+		let white = Vec3::new(1.0, 1.0, 1.0);
+		let diffuse: Arc<dyn Material> = Arc::new(Lambertian::from_color(white));
+
+		for roughness in [0.1, 0.3, 0.6, 1.0] {
+			let cloth = Cloth::from_colors(white, white, roughness);
+			let hit_record = hit_record(diffuse.clone());
+
+			for degrees in [0.0, 30.0, 60.0, 80.0, 85.0, 89.0] {
+				let theta_out = f64::to_radians(degrees);
+				let wo = Vec3::new(theta_out.sin(), 0.0, theta_out.cos());
+
+				// reflected fraction, integrated on a grid offset from the one of the albedo table
+				const STEPS: usize = 200;
+				let mut albedo = 0.0;
+				for i in 0..STEPS {
+					let cos_theta_in = (i as f64 + 0.25) / STEPS as f64;
+					let sin_theta_in = f64::sqrt(1.0 - cos_theta_in * cos_theta_in);
+					for j in 0..STEPS {
+						let phi = 2.0 * PI * (j as f64 + 0.25) / STEPS as f64;
+						let wi = Vec3::new(sin_theta_in * phi.cos(), sin_theta_in * phi.sin(), cos_theta_in);
+						albedo += cloth.brdf(&hit_record, wo, wi).x * cos_theta_in;
+					}
+				}
+				albedo *= 2.0 * PI / (STEPS * STEPS) as f64;
+
+				assert!(albedo < 1.01, "roughness {} at {} degrees reflects {}", roughness, degrees, albedo);
+			}
+		}
+	}
+
+	// Height rising along u
+	struct Ramp;
+
+	impl Texture for Ramp {
+		fn value_at(&self, u: f64, _v: f64, _p: Vec3, _normal: Vec3) -> Vec3 {
+			Vec3::new(u, u, u)
+		}
+	}
+
+	#[test]
+	fn bump_mapped_normals_stay_on_the_hit_side() {
+		let diffuse: Arc<dyn Material> = Arc::new(Lambertian::from_color(Vec3::new(0.5, 0.5, 0.5)));
+		let bumped = BumpMapped::new(diffuse.clone(), Arc::new(Ramp), 0.5);
+		let quad = Quad::new(Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), diffuse);
+
+		// the slope tilts the normal against u, and bumps rise towards the side that was hit
+		for (origin_z, expected) in [(1.0, Vec3::new(-0.5, 0.0, 1.0)), (-1.0, Vec3::new(-0.5, 0.0, -1.0))] {
+			let ray = Ray::new(Vec3::new(0.5, 0.5, origin_z), Vec3::new(0.0, 0.0, -origin_z), 0.0);
+			let hit = quad.hit(ray, Interval::new(0.001, f64::INFINITY)).unwrap();
+			let perturbed = bumped.perturb(&hit);
+
+			assert!(perturbed.normal.dot(perturbed.geometric_normal) > 0.0);
+			assert!((perturbed.normal - expected.unit()).length() < 1e-6);
+		}
+	}
+}
@@ -4,12 +4,27 @@ use crate::util::vec3::Vec3;
 pub struct Ray {
 	pub origin: Vec3,
 	pub direction: Vec3,
-	pub time: f64
+	pub time: f64,
+	pub differentials: Option<RayDifferentials>
+}
+
+// Rays through the neighbouring pixels in x and y, used to estimate the texture footprint
+#[derive(Debug, Copy, Clone)]
+pub struct RayDifferentials {
+	pub rx_origin: Vec3,
+	pub rx_direction: Vec3,
+	pub ry_origin: Vec3,
+	pub ry_direction: Vec3
 }
 
 impl Ray {
 	pub fn new(origin: Vec3, direction: Vec3, time: f64) -> Self {
-		Ray { origin, direction, time }
+		Ray { origin, direction, time, differentials: None }
+	}
+
+	pub fn with_differentials(mut self, differentials: RayDifferentials) -> Self {
+		self.differentials = Some(differentials);
+		self
 	}
 
 	pub fn at(&self, t: f64) -> Vec3 {
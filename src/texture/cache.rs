@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use image::ImageError;
+use log::info;
+use crate::texture::image_texture::{ColorSpace, ImageSettings, ImageTexture};
+
+// Loads every image file once per color space and hands out shared textures.
+// Requests with different sampling settings (filter, wrap, uv transform) share the decoded pixels.
+pub struct TextureCache {
+	entries: HashMap<(PathBuf, ColorSpace), Vec<Arc<ImageTexture>>>,
+	hits: usize,
+	misses: usize
+}
+
+impl TextureCache {
+	pub fn new() -> Self {
+		TextureCache { entries: HashMap::new(), hits: 0, misses: 0 }
+	}
+
+	pub fn load<P: AsRef<Path>>(&mut self, filepath: P) -> Result<Arc<ImageTexture>, ImageError> {
+		self.load_with_settings(filepath, ImageSettings::default())
+	}
+
+	pub fn load_with_settings<P: AsRef<Path>>(&mut self, filepath: P, settings: ImageSettings) -> Result<Arc<ImageTexture>, ImageError> {
+		// the same file reached through different relative paths should only be decoded once
+		let filepath = filepath.as_ref();
+		let path = std::fs::canonicalize(filepath).unwrap_or_else(|_| filepath.to_path_buf());
+
+		let textures = self.entries.entry((path.clone(), settings.color_space)).or_default();
+
+		if let Some(texture) = textures.iter().find(|texture| *texture.settings() == settings) {
+			self.hits += 1;
+			return Ok(texture.clone());
+		}
+
+		let texture = match textures.first().and_then(|texture| texture.derive(settings)) {
+			Some(texture) => {
+				self.hits += 1;
+				texture
+			}
+			None => {
+				self.misses += 1;
+				ImageTexture::with_settings(&path, settings)?
+			}
+		};
+
+		let texture = Arc::new(texture);
+		textures.push(texture.clone());
+
+		Ok(texture)
+	}
+
+	// Number of distinct decoded images
+	pub fn len(&self) -> usize {
+		self.entries.values().filter(|textures| !textures.is_empty()).count()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	// Bytes held by all cached pixels, shared mip levels counted once
+	pub fn memory_usage(&self) -> usize {
+		ImageTexture::combined_memory_usage(
+			self.entries.values().flatten().map(|texture| texture.as_ref())
+		)
+	}
+
+	pub fn log_usage(&self) {
+		info!(
+			"Texture cache: {} images, {:.1} MiB, {} hits, {} misses",
+			self.len(),
+			self.memory_usage() as f64 / (1024.0 * 1024.0),
+			self.hits,
+			self.misses
+		);
+
+		for ((path, color_space), textures) in &self.entries {
+			if textures.is_empty() { continue; }
+
+			info!(
+				"  {} ({:?}): {:.1} MiB, {} variants",
+				path.display(),
+				color_space,
+				ImageTexture::combined_memory_usage(textures.iter().map(|texture| texture.as_ref())) as f64 / (1024.0 * 1024.0),
+				textures.len()
+			);
+		}
+	}
+}
+
+impl Default for TextureCache {
+	fn default() -> Self {
+		TextureCache::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Cursor;
+	use crate::loader::test_file;
+	use crate::texture::image_texture::Filter;
+
+	fn image_file(name: &str) -> PathBuf {
+		let mut png = Vec::new();
+		image::RgbImage::from_pixel(8, 8, image::Rgb([200, 100, 50])).write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+		test_file(name, &png)
+	}
+
+	#[test]
+	fn shares_decoded_images() {
+		let path = image_file("shared.png");
+		let mut cache = TextureCache::new();
+
+		let first = cache.load(&path).unwrap();
+		let memory = cache.memory_usage();
+		assert!(Arc::ptr_eq(&first, &cache.load(&path).unwrap()));
+
+		// other sampling settings reuse the pixels, another color space decodes again
+		let nearest = ImageSettings { filter: Filter::NEAREST, ..ImageSettings::default() };
+		assert!(!Arc::ptr_eq(&first, &cache.load_with_settings(&path, nearest).unwrap()));
+		assert_eq!(cache.memory_usage(), memory);
+		cache.load_with_settings(&path, ImageSettings { color_space: ColorSpace::LINEAR, ..ImageSettings::default() }).unwrap();
+
+		assert_eq!(cache.len(), 2);
+		assert_eq!((cache.hits, cache.misses), (2, 2));
+	}
+
+	#[test]
+	fn missing_files_are_errors() {
+		let mut cache = TextureCache::new();
+		assert!(cache.load(std::env::temp_dir().join("grayshift-missing.png")).is_err());
+		assert_eq!(cache.misses, 1);
+	}
+}
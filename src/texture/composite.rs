@@ -0,0 +1,288 @@
+use std::sync::Arc;
+use crate::texture::procedural::ColorRamp;
+use crate::texture::texture::{Texture, UVDifferentials, UVTransform};
+use crate::util::vec3::Vec3;
+
+// Combinators building new textures out of existing ones.
+// Each forwards the footprint so prefiltered inputs stay filtered.
+
+pub struct MultiplyTexture {
+	a: Arc<dyn Texture>,
+	b: Arc<dyn Texture>
+}
+
+impl MultiplyTexture {
+	pub fn new(a: Arc<dyn Texture>, b: Arc<dyn Texture>) -> Self {
+		MultiplyTexture { a, b }
+	}
+}
+
+impl Texture for MultiplyTexture {
+	fn value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		self.a.value_at(u, v, p, normal) * self.b.value_at(u, v, p, normal)
+	}
+
+	fn filtered_value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		self.a.filtered_value_at(u, v, p, normal, differentials) * self.b.filtered_value_at(u, v, p, normal, differentials)
+	}
+}
+
+pub struct AddTexture {
+	a: Arc<dyn Texture>,
+	b: Arc<dyn Texture>
+}
+
+impl AddTexture {
+	pub fn new(a: Arc<dyn Texture>, b: Arc<dyn Texture>) -> Self {
+		AddTexture { a, b }
+	}
+}
+
+impl Texture for AddTexture {
+	fn value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		self.a.value_at(u, v, p, normal) + self.b.value_at(u, v, p, normal)
+	}
+
+	fn filtered_value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		self.a.filtered_value_at(u, v, p, normal, differentials) + self.b.filtered_value_at(u, v, p, normal, differentials)
+	}
+}
+
+// Blends from `a` (mask 0) to `b` (mask 1), per channel
+pub struct MixTexture {
+	a: Arc<dyn Texture>,
+	b: Arc<dyn Texture>,
+	mask: Arc<dyn Texture>
+}
+
+impl MixTexture {
+	pub fn new(a: Arc<dyn Texture>, b: Arc<dyn Texture>, mask: Arc<dyn Texture>) -> Self {
+		MixTexture { a, b, mask }
+	}
+
+	fn mix(a: Vec3, b: Vec3, mask: Vec3) -> Vec3 {
+		(Vec3::new(1.0, 1.0, 1.0) - mask) * a + mask * b
+	}
+}
+
+impl Texture for MixTexture {
+	fn value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		Self::mix(
+			self.a.value_at(u, v, p, normal),
+			self.b.value_at(u, v, p, normal),
+			self.mask.value_at(u, v, p, normal)
+		)
+	}
+
+	fn filtered_value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		Self::mix(
+			self.a.filtered_value_at(u, v, p, normal, differentials),
+			self.b.filtered_value_at(u, v, p, normal, differentials),
+			self.mask.filtered_value_at(u, v, p, normal, differentials)
+		)
+	}
+}
+
+// One minus the input
+pub struct InvertTexture {
+	texture: Arc<dyn Texture>
+}
+
+impl InvertTexture {
+	pub fn new(texture: Arc<dyn Texture>) -> Self {
+		InvertTexture { texture }
+	}
+}
+
+impl Texture for InvertTexture {
+	fn value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		Vec3::new(1.0, 1.0, 1.0) - self.texture.value_at(u, v, p, normal)
+	}
+
+	fn filtered_value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		Vec3::new(1.0, 1.0, 1.0) - self.texture.filtered_value_at(u, v, p, normal, differentials)
+	}
+}
+
+pub struct ClampTexture {
+	texture: Arc<dyn Texture>,
+	min: f64,
+	max: f64
+}
+
+impl ClampTexture {
+	pub fn new(texture: Arc<dyn Texture>, min: f64, max: f64) -> Self {
+		ClampTexture { texture, min, max }
+	}
+
+	fn clamp(&self, color: Vec3) -> Vec3 {
+		Vec3::new(
+			color.x.clamp(self.min, self.max),
+			color.y.clamp(self.min, self.max),
+			color.z.clamp(self.min, self.max)
+		)
+	}
+}
+
+impl Texture for ClampTexture {
+	fn value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		self.clamp(self.texture.value_at(u, v, p, normal))
+	}
+
+	fn filtered_value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		self.clamp(self.texture.filtered_value_at(u, v, p, normal, differentials))
+	}
+}
+
+// Maps the average of the input channels through a color ramp
+pub struct RampTexture {
+	texture: Arc<dyn Texture>,
+	ramp: ColorRamp
+}
+
+impl RampTexture {
+	pub fn new(texture: Arc<dyn Texture>, ramp: ColorRamp) -> Self {
+		RampTexture { texture, ramp }
+	}
+
+	fn remap(&self, color: Vec3) -> Vec3 {
+		self.ramp.sample((color.x + color.y + color.z) / 3.0)
+	}
+}
+
+impl Texture for RampTexture {
+	fn value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		self.remap(self.texture.value_at(u, v, p, normal))
+	}
+
+	fn filtered_value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		self.remap(self.texture.filtered_value_at(u, v, p, normal, differentials))
+	}
+}
+
+// Hue rotation (degrees) and saturation/value scaling
+pub struct HSVTexture {
+	texture: Arc<dyn Texture>,
+	hue_shift: f64,
+	saturation: f64,
+	value: f64
+}
+
+impl HSVTexture {
+	pub fn new(texture: Arc<dyn Texture>, hue_shift: f64, saturation: f64, value: f64) -> Self {
+		HSVTexture { texture, hue_shift, saturation, value }
+	}
+
+	fn adjust(&self, color: Vec3) -> Vec3 {
+		let (h, s, v) = rgb_to_hsv(color);
+		hsv_to_rgb(
+			(h + self.hue_shift).rem_euclid(360.0),
+			(s * self.saturation).clamp(0.0, 1.0),
+			v * self.value
+		)
+	}
+}
+
+impl Texture for HSVTexture {
+	fn value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		self.adjust(self.texture.value_at(u, v, p, normal))
+	}
+
+	fn filtered_value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		self.adjust(self.texture.filtered_value_at(u, v, p, normal, differentials))
+	}
+}
+
+// Applies scale, rotation (degrees, counter-clockwise) and offset to (u, v) before the lookup
+pub struct UVTransformTexture {
+	texture: Arc<dyn Texture>,
+	transform: UVTransform
+}
+
+impl UVTransformTexture {
+	pub fn new(texture: Arc<dyn Texture>, scale: (f64, f64), rotation: f64, offset: (f64, f64)) -> Self {
+		UVTransformTexture { texture, transform: UVTransform::new(scale, rotation, offset) }
+	}
+}
+
+impl Texture for UVTransformTexture {
+	fn value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		let (u, v) = self.transform.point(u, v);
+		self.texture.value_at(u, v, p, normal)
+	}
+
+	fn filtered_value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		let (u, v) = self.transform.point(u, v);
+		let (du_dx, dv_dx) = self.transform.direction(differentials.du_dx, differentials.dv_dx);
+		let (du_dy, dv_dy) = self.transform.direction(differentials.du_dy, differentials.dv_dy);
+
+		self.texture.filtered_value_at(u, v, p, normal, UVDifferentials { du_dx, dv_dx, du_dy, dv_dy, ..differentials })
+	}
+}
+
+// Scales then offsets the lookup position, for solid and procedural textures
+pub struct PositionTransformTexture {
+	texture: Arc<dyn Texture>,
+	scale: Vec3,
+	offset: Vec3
+}
+
+impl PositionTransformTexture {
+	pub fn new(texture: Arc<dyn Texture>, scale: Vec3, offset: Vec3) -> Self {
+		PositionTransformTexture { texture, scale, offset }
+	}
+}
+
+impl Texture for PositionTransformTexture {
+	fn value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		self.texture.value_at(u, v, p * self.scale + self.offset, normal)
+	}
+
+	fn filtered_value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		let differentials = UVDifferentials {
+			dp_dx: differentials.dp_dx * self.scale,
+			dp_dy: differentials.dp_dy * self.scale,
+			..differentials
+		};
+		self.texture.filtered_value_at(u, v, p * self.scale + self.offset, normal, differentials)
+	}
+}
+
+// Hue in degrees, saturation and value in [0, 1] (value may exceed 1 for HDR input)
+pub fn rgb_to_hsv(color: Vec3) -> (f64, f64, f64) {
+	let max = color.x.max(color.y).max(color.z);
+	let min = color.x.min(color.y).min(color.z);
+	let delta = max - min;
+
+	let hue = if delta <= 0.0 {
+		0.0
+	} else if max == color.x {
+		60.0 * ((color.y - color.z) / delta).rem_euclid(6.0)
+	} else if max == color.y {
+		60.0 * ((color.z - color.x) / delta + 2.0)
+	} else {
+		60.0 * ((color.x - color.y) / delta + 4.0)
+	};
+
+	let saturation = if max > 0.0 { delta / max } else { 0.0 };
+
+	(hue, saturation, max)
+}
+
+pub fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> Vec3 {
+	let chroma = value * saturation;
+	let sector = hue / 60.0;
+	let x = chroma * (1.0 - (sector.rem_euclid(2.0) - 1.0).abs());
+
+	let (r, g, b) = match sector as u32 {
+		0 => (chroma, x, 0.0),
+		1 => (x, chroma, 0.0),
+		2 => (0.0, chroma, x),
+		3 => (0.0, x, chroma),
+		4 => (x, 0.0, chroma),
+		_ => (chroma, 0.0, x)
+	};
+
+	let m = value - chroma;
+	Vec3::new(r + m, g + m, b + m)
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+use crate::texture::texture::{odd_cell_fraction, xor_fraction, SolidColorTexture, Texture, UVDifferentials};
+use crate::util::vec3::Vec3;
+
+// Textures defined in (u, v) rather than world space, for checking the parameterization
+// of primitives and imported meshes: stretching, flipped axes and seams show up directly.
+
+// Checkerboard with `checks_u` by `checks_v` cells over the unit square
+pub struct UVCheckerTexture {
+	checks_u: f64,
+	checks_v: f64,
+	even_texture: Arc<dyn Texture>,
+	odd_texture: Arc<dyn Texture>
+}
+
+impl UVCheckerTexture {
+	pub fn new(checks_u: f64, checks_v: f64, even_texture: Arc<dyn Texture>, odd_texture: Arc<dyn Texture>) -> Self {
+		UVCheckerTexture { checks_u, checks_v, even_texture, odd_texture }
+	}
+
+	pub fn from_colors(checks_u: f64, checks_v: f64, even_color: Vec3, odd_color: Vec3) -> Self {
+		UVCheckerTexture {
+			checks_u,
+			checks_v,
+			even_texture: Arc::new(SolidColorTexture::new(even_color)),
+			odd_texture: Arc::new(SolidColorTexture::new(odd_color))
+		}
+	}
+}
+
+impl Texture for UVCheckerTexture {
+	fn value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		let u_int = f64::floor(u * self.checks_u) as i64;
+		let v_int = f64::floor(v * self.checks_v) as i64;
+
+		if (u_int + v_int) % 2 == 0 {
+			self.even_texture.value_at(u, v, p, normal)
+		} else {
+			self.odd_texture.value_at(u, v, p, normal)
+		}
+	}
+
+	// Box filters the checkerboard over the pixel footprint so distant checks fade to grey instead of aliasing
+	fn filtered_value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		let width_u = self.checks_u * f64::max(differentials.du_dx.abs(), differentials.du_dy.abs());
+		let width_v = self.checks_v * f64::max(differentials.dv_dx.abs(), differentials.dv_dy.abs());
+
+		if width_u <= 0.0 || width_v <= 0.0 {
+			return self.value_at(u, v, p, normal);
+		}
+
+		let (s, t) = (u * self.checks_u, v * self.checks_v);
+
+		// fraction of odd cells along each axis, combined as an XOR of the two square waves
+		let odd_s = odd_cell_fraction(s - width_u / 2.0, s + width_u / 2.0);
+		let odd_t = odd_cell_fraction(t - width_v / 2.0, t + width_v / 2.0);
+		let odd = xor_fraction(odd_s, odd_t);
+
+		let even_color = self.even_texture.filtered_value_at(u, v, p, normal, differentials);
+		let odd_color = self.odd_texture.filtered_value_at(u, v, p, normal, differentials);
+
+		(1.0 - odd) * even_color + odd * odd_color
+	}
+}
+
+// Maps u to red and v to green, repeating outside [0, 1]
+#[derive(Default)]
+pub struct UVGradientTexture {}
+
+impl UVGradientTexture {
+	pub fn new() -> Self {
+		UVGradientTexture {}
+	}
+}
+
+impl Texture for UVGradientTexture {
+	fn value_at(&self, u: f64, v: f64, _p: Vec3, _normal: Vec3) -> Vec3 {
+		Vec3::new(u - u.floor(), v - v.floor(), 0.0)
+	}
+}
+
+// Lines at every 1 / `divisions` in u and v, `line_width` is a fraction of one cell
+pub struct GridTexture {
+	divisions: f64,
+	line_width: f64,
+	line_texture: Arc<dyn Texture>,
+	background_texture: Arc<dyn Texture>
+}
+
+impl GridTexture {
+	pub fn new(divisions: f64, line_width: f64, line_texture: Arc<dyn Texture>, background_texture: Arc<dyn Texture>) -> Self {
+		GridTexture { divisions, line_width, line_texture, background_texture }
+	}
+
+	pub fn from_colors(divisions: f64, line_width: f64, line_color: Vec3, background_color: Vec3) -> Self {
+		GridTexture {
+			divisions,
+			line_width,
+			line_texture: Arc::new(SolidColorTexture::new(line_color)),
+			background_texture: Arc::new(SolidColorTexture::new(background_color))
+		}
+	}
+
+	fn is_on_line(&self, u: f64, v: f64) -> bool {
+		let half_width = self.line_width / 2.0;
+
+		let distance_to_line = |x: f64| {
+			let cell = x * self.divisions;
+			let fraction = cell - cell.floor();
+			f64::min(fraction, 1.0 - fraction)
+		};
+
+		distance_to_line(u) < half_width || distance_to_line(v) < half_width
+	}
+}
+
+impl Texture for GridTexture {
+	fn value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		if self.is_on_line(u, v) {
+			self.line_texture.value_at(u, v, p, normal)
+		} else {
+			self.background_texture.value_at(u, v, p, normal)
+		}
+	}
+
+	fn filtered_value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		if self.is_on_line(u, v) {
+			self.line_texture.filtered_value_at(u, v, p, normal, differentials)
+		} else {
+			self.background_texture.filtered_value_at(u, v, p, normal, differentials)
+		}
+	}
+}
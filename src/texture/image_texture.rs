@@ -0,0 +1,683 @@
+use image::{ColorType, DynamicImage, GenericImageView, ImageError};
+use std::collections::HashSet;
+use std::ops::{Add, AddAssign, Mul};
+use std::path::Path;
+use std::sync::Arc;
+use crate::texture::texture::{Texture, UVDifferentials, UVTransform};
+use crate::util::vec3::Vec3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+	// color images (albedo, emission), decoded to linear on load.
+	// Floating point sources (.hdr, .exr) are always linear and never decoded.
+	SRGB,
+	// data images (normal maps, height maps, roughness), used as stored
+	LINEAR
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Filter {
+	NEAREST,
+	BILINEAR,
+	BICUBIC
+}
+
+// Filtering between mip levels when the footprint of a lookup is known
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MipFilter {
+	// always sample the full resolution image
+	NONE,
+	// isotropic, blends the two levels closest to the footprint width
+	TRILINEAR,
+	// anisotropic elliptically weighted average
+	EWA
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WrapMode {
+	REPEAT,
+	MIRROR,
+	CLAMP
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ImageSettings {
+	pub color_space: ColorSpace,
+	pub filter: Filter,
+	pub mip_filter: MipFilter,
+	// limits how stretched the EWA ellipse may get, trading blur for speed
+	pub max_anisotropy: f64,
+	pub wrap_mode: WrapMode,
+
+	// applied to (u, v) in order: scale, rotation (degrees, counter-clockwise), offset
+	pub uv_scale: (f64, f64),
+	pub uv_rotation: f64,
+	pub uv_offset: (f64, f64)
+}
+
+impl Default for ImageSettings {
+	fn default() -> Self {
+		ImageSettings {
+			color_space: ColorSpace::SRGB,
+			filter: Filter::BILINEAR,
+			mip_filter: MipFilter::TRILINEAR,
+			max_anisotropy: 8.0,
+			wrap_mode: WrapMode::REPEAT,
+			uv_scale: (1.0, 1.0),
+			uv_rotation: 0.0,
+			uv_offset: (0.0, 0.0)
+		}
+	}
+}
+
+// Linear color with straight (not premultiplied) alpha
+#[derive(Debug, Copy, Clone)]
+struct Rgba {
+	r: f32,
+	g: f32,
+	b: f32,
+	a: f32
+}
+
+impl Rgba {
+	const ZERO: Rgba = Rgba { r: 0.0, g: 0.0, b: 0.0, a: 0.0 };
+
+	fn color(&self) -> Vec3 {
+		Vec3::new(self.r as f64, self.g as f64, self.b as f64)
+	}
+
+	fn max_zero(&self) -> Rgba {
+		Rgba { r: self.r.max(0.0), g: self.g.max(0.0), b: self.b.max(0.0), a: self.a.max(0.0) }
+	}
+}
+
+impl Add for Rgba {
+	type Output = Self;
+	fn add(self, rhs: Self) -> Self::Output {
+		Rgba { r: self.r + rhs.r, g: self.g + rhs.g, b: self.b + rhs.b, a: self.a + rhs.a }
+	}
+}
+
+impl AddAssign for Rgba {
+	fn add_assign(&mut self, rhs: Self) {
+		*self = *self + rhs;
+	}
+}
+
+impl Mul<Rgba> for f64 {
+	type Output = Rgba;
+	fn mul(self, rhs: Rgba) -> Self::Output {
+		let w = self as f32;
+		Rgba { r: w * rhs.r, g: w * rhs.g, b: w * rhs.b, a: w * rhs.a }
+	}
+}
+
+// One level of the mip pyramid, row major starting at the top of the image
+struct MipLevel {
+	pixels: Vec<Rgba>,
+	width: usize,
+	height: usize
+}
+
+impl MipLevel {
+	fn memory_usage(&self) -> usize {
+		self.pixels.capacity() * std::mem::size_of::<Rgba>()
+	}
+}
+
+pub struct ImageTexture {
+	// full resolution image first, each following level half the size of the previous.
+	// Shared between textures derived from the same decoded image.
+	levels: Vec<Arc<MipLevel>>,
+	settings: ImageSettings,
+	uv_transform: UVTransform
+}
+
+impl ImageTexture {
+	// 8 and 16 bit images as well as floating point .hdr and .exr files are kept as linear f32
+	pub fn with_settings<P: AsRef<Path>>(filepath: P, settings: ImageSettings) -> Result<Self, ImageError> {
+		Ok(Self::from_image(image::open(filepath)?, settings))
+	}
+
+	pub fn from_image(image: DynamicImage, settings: ImageSettings) -> Self {
+		let (width, height) = image.dimensions();
+
+		let is_float = matches!(image.color(), ColorType::Rgb32F | ColorType::Rgba32F);
+		let decode = settings.color_space == ColorSpace::SRGB && !is_float;
+
+		let pixels = image.to_rgba32f().pixels().map(|pixel| {
+			let [r, g, b, a] = pixel.0;
+			if decode {
+				Rgba { r: srgb_to_linear(r), g: srgb_to_linear(g), b: srgb_to_linear(b), a }
+			} else {
+				Rgba { r, g, b, a }
+			}
+		}).collect();
+
+		let mut texture = ImageTexture {
+			levels: vec![Arc::new(MipLevel { pixels, width: width as usize, height: height as usize })],
+			settings,
+			uv_transform: UVTransform::new(settings.uv_scale, settings.uv_rotation, settings.uv_offset)
+		};
+		texture.build_mip_pyramid();
+
+		texture
+	}
+
+	// Texture with different sampling settings sharing the decoded pixels of this one.
+	// None when the color space differs, since the pixels would have to be decoded again.
+	pub fn derive(&self, settings: ImageSettings) -> Option<Self> {
+		if settings.color_space != self.settings.color_space { return None; }
+
+		// the pyramid depends on the wrap mode at odd sized edges
+		let reuse_mips = settings.mip_filter != MipFilter::NONE
+			&& self.levels.len() > 1
+			&& settings.wrap_mode == self.settings.wrap_mode;
+
+		let levels = if reuse_mips { self.levels.clone() } else { vec![self.levels[0].clone()] };
+		let uv_transform = UVTransform::new(settings.uv_scale, settings.uv_rotation, settings.uv_offset);
+
+		let mut texture = ImageTexture { levels, settings, uv_transform };
+		if !reuse_mips { texture.build_mip_pyramid(); }
+
+		Some(texture)
+	}
+
+	pub fn settings(&self) -> &ImageSettings {
+		&self.settings
+	}
+
+	// Bytes held by a set of textures, counting shared levels once
+	pub fn combined_memory_usage<'a>(textures: impl IntoIterator<Item = &'a ImageTexture>) -> usize {
+		let mut seen = HashSet::new();
+
+		textures.into_iter()
+			.flat_map(|texture| texture.levels.iter())
+			.filter(|level| seen.insert(Arc::as_ptr(level)))
+			.map(|level| level.memory_usage())
+			.sum()
+	}
+
+	// Repeatedly box filters the last level down to a single texel
+	fn build_mip_pyramid(&mut self) {
+		if self.settings.mip_filter == MipFilter::NONE { return; }
+
+		loop {
+			let level = self.levels.len() - 1;
+			let (width, height) = (self.levels[level].width, self.levels[level].height);
+			if width <= 1 && height <= 1 { break; }
+
+			let next_width = usize::max(1, width.div_ceil(2));
+			let next_height = usize::max(1, height.div_ceil(2));
+
+			let mut pixels = Vec::with_capacity(next_width * next_height);
+			for j in 0..next_height {
+				for i in 0..next_width {
+					let (x, y) = (2 * i as i64, 2 * j as i64);
+					pixels.push(0.25 * (
+						self.texel(level, x, y) + self.texel(level, x + 1, y)
+							+ self.texel(level, x, y + 1) + self.texel(level, x + 1, y + 1)
+					));
+				}
+			}
+
+			self.levels.push(Arc::new(MipLevel { pixels, width: next_width, height: next_height }));
+		}
+	}
+
+	fn wrap(&self, i: i64, size: usize) -> usize {
+		let size = size as i64;
+		let wrapped = match self.settings.wrap_mode {
+			WrapMode::REPEAT => i.rem_euclid(size),
+			WrapMode::MIRROR => {
+				let m = i.rem_euclid(2 * size);
+				if m >= size { 2 * size - 1 - m } else { m }
+			}
+			WrapMode::CLAMP => i.clamp(0, size - 1)
+		};
+
+		wrapped as usize
+	}
+
+	fn texel(&self, level: usize, i: i64, j: i64) -> Rgba {
+		let level = &self.levels[level];
+		let x = self.wrap(i, level.width);
+		let y = self.wrap(j, level.height);
+		level.pixels[y * level.width + x]
+	}
+
+	// Samples a mip level with the configured filter, (u, v) already transformed
+	fn sample_level(&self, level: usize, u: f64, v: f64) -> Rgba {
+		// image rows start at the top, v starts at the bottom
+		let x = u * self.levels[level].width as f64;
+		let y = (1.0 - v) * self.levels[level].height as f64;
+
+		match self.settings.filter {
+			Filter::NEAREST => self.sample_nearest(level, x, y),
+			Filter::BILINEAR => self.sample_bilinear(level, x, y),
+			Filter::BICUBIC => self.sample_bicubic(level, x, y)
+		}
+	}
+
+	fn sample_nearest(&self, level: usize, x: f64, y: f64) -> Rgba {
+		self.texel(level, x.floor() as i64, y.floor() as i64)
+	}
+
+	fn sample_bilinear(&self, level: usize, x: f64, y: f64) -> Rgba {
+		// texel centers sit at half integer coordinates
+		let x = x - 0.5;
+		let y = y - 0.5;
+		let i = x.floor();
+		let j = y.floor();
+		let fx = x - i;
+		let fy = y - j;
+		let (i, j) = (i as i64, j as i64);
+
+		(1.0 - fx) * (1.0 - fy) * self.texel(level, i, j)
+			+ fx * (1.0 - fy) * self.texel(level, i + 1, j)
+			+ (1.0 - fx) * fy * self.texel(level, i, j + 1)
+			+ fx * fy * self.texel(level, i + 1, j + 1)
+	}
+
+	fn sample_bicubic(&self, level: usize, x: f64, y: f64) -> Rgba {
+		let x = x - 0.5;
+		let y = y - 0.5;
+		let i = x.floor();
+		let j = y.floor();
+		let weights_x = catmull_rom_weights(x - i);
+		let weights_y = catmull_rom_weights(y - j);
+		let (i, j) = (i as i64, j as i64);
+
+		let mut color = Rgba::ZERO;
+		for (dj, weight_y) in weights_y.iter().enumerate() {
+			for (di, weight_x) in weights_x.iter().enumerate() {
+				color += weight_x * weight_y * self.texel(level, i + di as i64 - 1, j + dj as i64 - 1);
+			}
+		}
+
+		// Catmull-Rom overshoots near sharp edges
+		color.max_zero()
+	}
+
+	// Blends the two levels around a fractional level of detail
+	fn sample_between_levels(&self, lod: f64, sample: impl Fn(usize) -> Rgba) -> Rgba {
+		let max_level = self.levels.len() - 1;
+		let lod = lod.clamp(0.0, max_level as f64);
+		let lower = lod.floor() as usize;
+		if lower >= max_level { return sample(max_level); }
+
+		let fraction = lod - lower as f64;
+		(1.0 - fraction) * sample(lower) + fraction * sample(lower + 1)
+	}
+
+	fn sample_trilinear(&self, u: f64, v: f64, d_x: (f64, f64), d_y: (f64, f64)) -> Rgba {
+		let (width, height) = (self.levels[0].width as f64, self.levels[0].height as f64);
+
+		// footprint width in texels of the full resolution image
+		let texels_x = f64::hypot(d_x.0 * width, d_x.1 * height);
+		let texels_y = f64::hypot(d_y.0 * width, d_y.1 * height);
+		let footprint = f64::max(texels_x, texels_y);
+		if footprint <= 1.0 { return self.sample_level(0, u, v); }
+
+		self.sample_between_levels(footprint.log2(), |level| self.sample_level(level, u, v))
+	}
+
+	fn sample_ewa(&self, u: f64, v: f64, d_x: (f64, f64), d_y: (f64, f64)) -> Rgba {
+		let (width, height) = (self.levels[0].width as f64, self.levels[0].height as f64);
+
+		// ellipse axes in texels of the full resolution image, major axis first.
+		// Texel rows run against v, as in the lookup.
+		let mut major = (d_x.0 * width, -d_x.1 * height);
+		let mut minor = (d_y.0 * width, -d_y.1 * height);
+		if f64::hypot(major.0, major.1) < f64::hypot(minor.0, minor.1) {
+			std::mem::swap(&mut major, &mut minor);
+		}
+		let major_length = f64::hypot(major.0, major.1);
+		let mut minor_length = f64::hypot(minor.0, minor.1);
+		if minor_length == 0.0 { return self.sample_trilinear(u, v, d_x, d_y); }
+
+		// widen overly eccentric ellipses so the number of texels visited stays bounded
+		if minor_length * self.settings.max_anisotropy < major_length && minor_length > 0.0 {
+			let scale = major_length / (minor_length * self.settings.max_anisotropy);
+			minor = (minor.0 * scale, minor.1 * scale);
+			minor_length *= scale;
+		}
+
+		if minor_length <= 1.0 {
+			return self.ewa(0, u, v, major, minor);
+		}
+
+		// the level where the minor axis covers about one texel
+		self.sample_between_levels(minor_length.log2(), |level| {
+			let scale = 1.0 / (1 << level) as f64;
+			let major = (major.0 * scale, major.1 * scale);
+			let minor = (minor.0 * scale, minor.1 * scale);
+			self.ewa(level, u, v, major, minor)
+		})
+	}
+
+	// Gaussian weighted average of the texels inside the ellipse spanned by the two axes (in texels of the level)
+	fn ewa(&self, level: usize, u: f64, v: f64, axis_0: (f64, f64), axis_1: (f64, f64)) -> Rgba {
+		const ALPHA: f64 = 2.0;
+
+		let x = u * self.levels[level].width as f64 - 0.5;
+		let y = (1.0 - v) * self.levels[level].height as f64 - 0.5;
+
+		// implicit ellipse a*x^2 + b*x*y + c*y^2 < 1, padded by one texel to avoid gaps
+		let mut a = axis_0.1 * axis_0.1 + axis_1.1 * axis_1.1 + 1.0;
+		let mut b = -2.0 * (axis_0.0 * axis_0.1 + axis_1.0 * axis_1.1);
+		let mut c = axis_0.0 * axis_0.0 + axis_1.0 * axis_1.0 + 1.0;
+		let inv_f = 1.0 / (a * c - b * b * 0.25);
+		a *= inv_f;
+		b *= inv_f;
+		c *= inv_f;
+
+		// bounding box of the ellipse
+		let det = -b * b + 4.0 * a * c;
+		let inv_det = 1.0 / det;
+		let x_extent = 2.0 * inv_det * f64::sqrt(det * c);
+		let y_extent = 2.0 * inv_det * f64::sqrt(det * a);
+
+		let x_min = (x - x_extent).ceil() as i64;
+		let x_max = (x + x_extent).floor() as i64;
+		let y_min = (y - y_extent).ceil() as i64;
+		let y_max = (y + y_extent).floor() as i64;
+
+		let mut sum = Rgba::ZERO;
+		let mut weight_sum = 0.0;
+		for j in y_min..=y_max {
+			let dy = j as f64 - y;
+			for i in x_min..=x_max {
+				let dx = i as f64 - x;
+				let r_sq = a * dx * dx + b * dx * dy + c * dy * dy;
+				if r_sq < 1.0 {
+					let weight = f64::exp(-ALPHA * r_sq) - f64::exp(-ALPHA);
+					sum += weight * self.texel(level, i, j);
+					weight_sum += weight;
+				}
+			}
+		}
+
+		if weight_sum <= 0.0 { return self.sample_level(level, u, v); }
+		(1.0 / weight_sum) * sum
+	}
+}
+
+impl ImageTexture {
+	fn lookup(&self, u: f64, v: f64) -> Rgba {
+		let (u, v) = self.uv_transform.point(u, v);
+		self.sample_level(0, u, v)
+	}
+
+	fn filtered_lookup(&self, u: f64, v: f64, differentials: UVDifferentials) -> Rgba {
+		if self.levels.len() == 1 { return self.lookup(u, v); }
+
+		let (u, v) = self.uv_transform.point(u, v);
+		let d_x = self.uv_transform.direction(differentials.du_dx, differentials.dv_dx);
+		let d_y = self.uv_transform.direction(differentials.du_dy, differentials.dv_dy);
+
+		match self.settings.mip_filter {
+			MipFilter::NONE => self.sample_level(0, u, v),
+			MipFilter::TRILINEAR => self.sample_trilinear(u, v, d_x, d_y),
+			MipFilter::EWA => self.sample_ewa(u, v, d_x, d_y)
+		}
+	}
+
+	// Opacity from the alpha channel, 1 for images without one
+	pub fn alpha_at(&self, u: f64, v: f64) -> f64 {
+		if self.levels[0].pixels.is_empty() { return 1.0; }
+		self.lookup(u, v).a as f64
+	}
+}
+
+impl Texture for ImageTexture {
+	fn value_at(&self, u: f64, v: f64, _p: Vec3, _normal: Vec3) -> Vec3 {
+		if self.levels[0].pixels.is_empty() { return Vec3::new(0.0, 1.0, 1.0); }
+		self.lookup(u, v).color()
+	}
+
+	fn filtered_value_at(&self, u: f64, v: f64, _p: Vec3, _normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		if self.levels[0].pixels.is_empty() { return Vec3::new(0.0, 1.0, 1.0); }
+		self.filtered_lookup(u, v, differentials).color()
+	}
+}
+
+// Alpha channel of an image as a grey texture, e.g. for an AlphaMask cutout
+pub struct ImageAlphaTexture {
+	image: Arc<ImageTexture>
+}
+
+impl ImageAlphaTexture {
+	pub fn new(image: Arc<ImageTexture>) -> Self {
+		ImageAlphaTexture { image }
+	}
+}
+
+impl Texture for ImageAlphaTexture {
+	fn value_at(&self, u: f64, v: f64, _p: Vec3, _normal: Vec3) -> Vec3 {
+		let alpha = self.image.alpha_at(u, v);
+		Vec3::new(alpha, alpha, alpha)
+	}
+
+	fn filtered_value_at(&self, u: f64, v: f64, _p: Vec3, _normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		if self.image.levels[0].pixels.is_empty() { return Vec3::new(1.0, 1.0, 1.0); }
+		let alpha = self.image.filtered_lookup(u, v, differentials).a as f64;
+		Vec3::new(alpha, alpha, alpha)
+	}
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+	if c <= 0.04045 {
+		c / 12.92
+	} else {
+		((c + 0.055) / 1.055).powf(2.4)
+	}
+}
+
+fn catmull_rom_weights(t: f64) -> [f64; 4] {
+	let t2 = t * t;
+	let t3 = t2 * t;
+
+	[
+		0.5 * (-t3 + 2.0 * t2 - t),
+		0.5 * (3.0 * t3 - 5.0 * t2 + 2.0),
+		0.5 * (-3.0 * t3 + 4.0 * t2 + t),
+		0.5 * (t3 - t2)
+	]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Cursor;
+	use image::{ImageFormat, Rgb, RgbImage};
+	use crate::loader::test_file;
+
+	fn texture(image: RgbImage, settings: ImageSettings) -> ImageTexture {
+		ImageTexture::from_image(DynamicImage::ImageRgb8(image), settings)
+	}
+
+	// Black on the left texel, white on the right, stored linear
+	fn black_white(wrap_mode: WrapMode) -> ImageTexture {
+		let image = RgbImage::from_fn(2, 1, |x, _| Rgb([255 * x as u8; 3]));
+		texture(image, ImageSettings { color_space: ColorSpace::LINEAR, mip_filter: MipFilter::NONE, wrap_mode, ..ImageSettings::default() })
+	}
+
+	fn value(texture: &ImageTexture, u: f64, v: f64) -> f64 {
+		texture.value_at(u, v, Vec3::ZERO, Vec3::ZERO).x
+	}
+
+	#[test]
+	fn srgb_decoding() {
+		assert_eq!(srgb_to_linear(0.0), 0.0);
+		assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-6);
+		assert!((srgb_to_linear(0.5) - 0.214_041).abs() < 1e-5);
+
+		// both pieces meet at the threshold
+		assert!((0.04045 / 12.92 - ((0.04045f32 + 0.055) / 1.055).powf(2.4)).abs() < 1e-6);
+
+		let mut previous = 0.0;
+		for step in 1..=255 {
+			let decoded = srgb_to_linear(step as f32 / 255.0);
+			assert!(decoded > previous);
+			previous = decoded;
+		}
+	}
+
+	#[test]
+	fn color_space_decides_decoding() {
+		let gray = RgbImage::from_pixel(1, 1, Rgb([128; 3]));
+		let stored = 128.0 / 255.0;
+
+		let srgb = texture(gray.clone(), ImageSettings::default());
+		assert!((value(&srgb, 0.5, 0.5) - srgb_to_linear(stored) as f64).abs() < 1e-6);
+
+		let linear = texture(gray, ImageSettings { color_space: ColorSpace::LINEAR, ..ImageSettings::default() });
+		assert!((value(&linear, 0.5, 0.5) - stored as f64).abs() < 1e-6);
+
+		// floating point images are linear whatever the settings say
+		let float = DynamicImage::ImageRgb32F(image::Rgb32FImage::from_pixel(1, 1, Rgb([0.5; 3])));
+		assert!((value(&ImageTexture::from_image(float, ImageSettings::default()), 0.5, 0.5) - 0.5).abs() < 1e-6);
+	}
+
+	#[test]
+	fn bilinear_between_texel_centers() {
+		let texture = black_white(WrapMode::CLAMP);
+		assert!(value(&texture, 0.25, 0.5).abs() < 1e-6);
+		assert!((value(&texture, 0.5, 0.5) - 0.5).abs() < 1e-6);
+		assert!((value(&texture, 0.75, 0.5) - 1.0).abs() < 1e-6);
+	}
+
+	#[test]
+	fn wrap_modes_past_the_edge() {
+		// a quarter texel left of the image, halfway from the left texel center to the edge
+		let u = -0.125;
+		assert!((value(&black_white(WrapMode::REPEAT), u, 0.5) - 0.75).abs() < 1e-6);
+		assert!(value(&black_white(WrapMode::MIRROR), u, 0.5).abs() < 1e-6);
+		assert!(value(&black_white(WrapMode::CLAMP), u, 0.5).abs() < 1e-6);
+
+		assert!((value(&black_white(WrapMode::REPEAT), 1.25, 0.5) - value(&black_white(WrapMode::REPEAT), 0.25, 0.5)).abs() < 1e-6);
+		assert!((value(&black_white(WrapMode::MIRROR), 1.25, 0.5) - 1.0).abs() < 1e-6);
+	}
+
+	#[test]
+	fn catmull_rom_weights_sum_to_one() {
+		for step in 0..=10 {
+			let weights = catmull_rom_weights(step as f64 / 10.0);
+			assert!((weights.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+		}
+		assert_eq!(catmull_rom_weights(0.0), [0.0, 1.0, 0.0, 0.0]);
+	}
+
+	// Diagonal stripes 8 texels wide, constant along +x +y in image space, i.e. along +u -v
+	fn stripes(mip_filter: MipFilter) -> ImageTexture {
+		let image = RgbImage::from_fn(64, 64, |x, y| {
+			let white = (x as i64 - y as i64).rem_euclid(16) < 8;
+			Rgb([if white { 255 } else { 0 }; 3])
+		});
+		texture(image, ImageSettings { color_space: ColorSpace::LINEAR, mip_filter, ..ImageSettings::default() })
+	}
+
+	fn footprint(d_x: (f64, f64), d_y: (f64, f64)) -> UVDifferentials {
+		UVDifferentials { du_dx: d_x.0, dv_dx: d_x.1, du_dy: d_y.0, dv_dy: d_y.1, ..UVDifferentials::ZERO }
+	}
+
+	fn filtered(texture: &ImageTexture, u: f64, v: f64, differentials: UVDifferentials) -> f64 {
+		texture.filtered_value_at(u, v, Vec3::ZERO, Vec3::ZERO, differentials).x
+	}
+
+	#[test]
+	fn ewa_follows_the_footprint_orientation() {
+		let texture = stripes(MipFilter::EWA);
+		// middle of a white stripe
+		let (u, v) = (35.5 / 64.0, 0.5);
+		let (along, across) = (6.0 / 64.0 / 2f64.sqrt(), 0.3 / 64.0 / 2f64.sqrt());
+
+		// stretched along the stripe the average stays white
+		let along_stripe = filtered(&texture, u, v, footprint((along, -along), (across, across)));
+		assert!(along_stripe > 0.95, "{}", along_stripe);
+
+		// the same ellipse turned across the stripes mixes in black
+		let across_stripes = filtered(&texture, u, v, footprint((along, along), (across, -across)));
+		assert!(across_stripes < 0.8, "{}", across_stripes);
+	}
+
+	#[test]
+	fn ewa_weights_average_large_footprints() {
+		// a 1 texel checker averages to gray once the footprint covers many texels
+		let image = RgbImage::from_fn(64, 64, |x, y| Rgb([if (x + y) % 2 == 0 { 255 } else { 0 }; 3]));
+		let settings = ImageSettings { color_space: ColorSpace::LINEAR, mip_filter: MipFilter::EWA, ..ImageSettings::default() };
+		let checker = texture(image, settings);
+
+		for (u, v) in [(0.3, 0.6), (0.51, 0.12), (0.9, 0.9)] {
+			let value = filtered(&checker, u, v, footprint((8.0 / 64.0, 0.0), (0.0, 8.0 / 64.0)));
+			assert!((value - 0.5).abs() < 0.02, "{}", value);
+		}
+
+		// constant images come back unchanged, the weights are normalized
+		let white = texture(RgbImage::from_pixel(16, 16, Rgb([255; 3])), settings);
+		let value = filtered(&white, 0.5, 0.5, footprint((0.3, 0.1), (-0.02, 0.05)));
+		assert!((value - 1.0).abs() < 1e-6);
+	}
+
+	#[test]
+	fn trilinear_blurs_by_footprint_width() {
+		let texture = stripes(MipFilter::TRILINEAR);
+		let (u, v) = (35.5 / 64.0, 0.5);
+
+		// under a texel wide the full resolution image is sampled
+		assert!((filtered(&texture, u, v, footprint((0.5 / 64.0, 0.0), (0.0, 0.5 / 64.0))) - 1.0).abs() < 1e-6);
+		assert!((filtered(&texture, u, v, footprint((1.0, 0.0), (0.0, 1.0))) - 0.5).abs() < 1e-6);
+	}
+
+	#[test]
+	fn mip_pyramid_down_to_one_texel() {
+		let texture = stripes(MipFilter::TRILINEAR);
+		let sizes: Vec<(usize, usize)> = texture.levels.iter().map(|level| (level.width, level.height)).collect();
+		assert_eq!(sizes, vec![(64, 64), (32, 32), (16, 16), (8, 8), (4, 4), (2, 2), (1, 1)]);
+
+		let odd = ImageTexture::from_image(DynamicImage::ImageRgb8(RgbImage::new(5, 3)), ImageSettings::default());
+		assert_eq!(odd.levels.iter().map(|level| (level.width, level.height)).last(), Some((1, 1)));
+		assert_eq!(odd.levels[1].width, 3);
+	}
+
+	fn encode(image: DynamicImage, format: ImageFormat) -> Vec<u8> {
+		let mut bytes = Vec::new();
+		image.write_to(&mut Cursor::new(&mut bytes), format).unwrap();
+		bytes
+	}
+
+	#[test]
+	fn sixteen_bit_files_keep_their_precision() {
+		// between two 8 bit steps
+		let stored = 1000u16;
+		let image = DynamicImage::ImageRgb16(image::ImageBuffer::from_pixel(1, 1, Rgb([stored; 3])));
+		let path = test_file("sixteen.png", &encode(image, ImageFormat::Png));
+
+		let texture = ImageTexture::with_settings(path, ImageSettings { color_space: ColorSpace::LINEAR, ..ImageSettings::default() }).unwrap();
+		assert!((value(&texture, 0.5, 0.5) - stored as f64 / 65535.0).abs() < 1e-7);
+	}
+
+	#[test]
+	fn hdr_files_keep_values_above_one() {
+		let image = DynamicImage::ImageRgb32F(image::Rgb32FImage::from_pixel(2, 2, Rgb([4.0, 0.5, 16.0])));
+		let path = test_file("bright.hdr", &encode(image, ImageFormat::Hdr));
+
+		// decoded as linear even with the default sRGB settings
+		let color = ImageTexture::with_settings(path, ImageSettings::default()).unwrap().value_at(0.5, 0.5, Vec3::ZERO, Vec3::ZERO);
+		assert!((color - Vec3::new(4.0, 0.5, 16.0)).length() < 0.1);
+	}
+
+	#[test]
+	fn alpha_is_a_separate_channel() {
+		let image = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 64])));
+		let texture = Arc::new(ImageTexture::from_image(image, ImageSettings::default()));
+
+		// straight alpha leaves the color alone
+		assert!((value(&texture, 0.5, 0.5) - 1.0).abs() < 1e-6);
+		assert!((texture.alpha_at(0.5, 0.5) - 64.0 / 255.0).abs() < 1e-6);
+
+		let alpha = ImageAlphaTexture::new(texture).value_at(0.5, 0.5, Vec3::ZERO, Vec3::ZERO);
+		assert!((alpha.x - 64.0 / 255.0).abs() < 1e-6);
+	}
+}
@@ -0,0 +1,8 @@
+pub mod cache;
+pub mod composite;
+pub mod debug;
+pub mod image_texture;
+pub mod procedural;
+pub mod projection;
+pub mod texture;
+pub mod voxel;
\ No newline at end of file
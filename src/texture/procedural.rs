@@ -0,0 +1,264 @@
+use noise::{NoiseFn, Perlin};
+use crate::texture::texture::Texture;
+use crate::util::vec3::Vec3;
+
+pub enum Pattern {
+	// fractal Brownian motion, smooth clouds
+	FBM,
+	// ridged multifractal, sharp mountain ridges
+	RIDGED,
+	// distance to the closest cell point
+	WORLEY,
+	// flat random value per cell
+	VORONOI,
+	// veins along z distorted by turbulence
+	MARBLE,
+	// concentric rings around the y axis distorted by turbulence
+	WOOD
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct NoiseSettings {
+	// frequency of the first octave
+	pub scale: f64,
+	pub octaves: u32,
+	// frequency multiplier between octaves
+	pub lacunarity: f64,
+	// amplitude multiplier between octaves
+	pub gain: f64,
+	pub seed: u32,
+	// strength of the turbulence in marble and wood
+	pub distortion: f64,
+	// offset of the lookup point by another noise field, 0 disables domain warping
+	pub warp: f64
+}
+
+impl Default for NoiseSettings {
+	fn default() -> Self {
+		NoiseSettings {
+			scale: 1.0,
+			octaves: 6,
+			lacunarity: 2.0,
+			gain: 0.5,
+			seed: 0,
+			distortion: 10.0,
+			warp: 0.0
+		}
+	}
+}
+
+// Piecewise linear map from [0, 1] to colors
+pub struct ColorRamp {
+	stops: Vec<(f64, Vec3)>
+}
+
+impl ColorRamp {
+	// stops at NaN or infinity have no place on the ramp and are dropped
+	pub fn new(mut stops: Vec<(f64, Vec3)>) -> Self {
+		stops.retain(|stop| stop.0.is_finite());
+		stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+		ColorRamp { stops }
+	}
+
+	pub fn grayscale() -> Self {
+		ColorRamp::new(vec![
+			(0.0, Vec3::ZERO),
+			(1.0, Vec3::new(1.0, 1.0, 1.0))
+		])
+	}
+
+	pub fn sample(&self, t: f64) -> Vec3 {
+		let Some(first) = self.stops.first() else { return Vec3::ZERO; };
+		if t <= first.0 { return first.1; }
+
+		for pair in self.stops.windows(2) {
+			let (t_0, color_0) = pair[0];
+			let (t_1, color_1) = pair[1];
+			if t <= t_1 {
+				let fraction = if t_1 > t_0 { (t - t_0) / (t_1 - t_0) } else { 1.0 };
+				return (1.0 - fraction) * color_0 + fraction * color_1;
+			}
+		}
+
+		self.stops[self.stops.len() - 1].1
+	}
+}
+
+pub struct ProceduralTexture {
+	pattern: Pattern,
+	settings: NoiseSettings,
+	ramp: ColorRamp,
+	noise: Perlin,
+	warp_noise: Perlin
+}
+
+impl ProceduralTexture {
+	pub fn new(pattern: Pattern, settings: NoiseSettings, ramp: ColorRamp) -> Self {
+		ProceduralTexture {
+			pattern,
+			settings,
+			ramp,
+			noise: Perlin::new(settings.seed),
+			warp_noise: Perlin::new(settings.seed.wrapping_add(1))
+		}
+	}
+
+	// Pattern value in [0, 1] before the color ramp
+	pub fn scalar_at(&self, p: Vec3) -> f64 {
+		let p = self.warp(p * self.settings.scale);
+
+		let value = match self.pattern {
+			Pattern::FBM => 0.5 * (1.0 + self.fbm(p)),
+			Pattern::RIDGED => self.ridged(p),
+			Pattern::WORLEY => self.worley(p).0,
+			Pattern::VORONOI => self.worley(p).1,
+			Pattern::MARBLE => 0.5 * (1.0 + f64::sin(p.z + self.settings.distortion * self.turbulence(p))),
+			Pattern::WOOD => {
+				let rings = f64::sqrt(p.x * p.x + p.z * p.z) + 0.1 * self.settings.distortion * self.turbulence(p);
+				rings - rings.floor()
+			}
+		};
+
+		value.clamp(0.0, 1.0)
+	}
+
+	fn warp(&self, p: Vec3) -> Vec3 {
+		if self.settings.warp == 0.0 { return p; }
+
+		let offset = Vec3::new(
+			self.warp_noise.get([p.x, p.y, p.z]),
+			self.warp_noise.get([p.x + 5.2, p.y + 1.3, p.z + 2.8]),
+			self.warp_noise.get([p.x + 1.7, p.y + 9.2, p.z + 4.1])
+		);
+
+		p + self.settings.warp * offset
+	}
+
+	// Roughly in [-1, 1]
+	fn fbm(&self, p: Vec3) -> f64 {
+		let mut sum = 0.0;
+		let mut amplitude = 1.0;
+		let mut total_amplitude = 0.0;
+		let mut sample_point = p;
+
+		for _ in 0..self.settings.octaves {
+			sum += amplitude * self.noise.get([sample_point.x, sample_point.y, sample_point.z]);
+			total_amplitude += amplitude;
+			amplitude *= self.settings.gain;
+			sample_point *= self.settings.lacunarity;
+		}
+
+		if total_amplitude > 0.0 { sum / total_amplitude } else { 0.0 }
+	}
+
+	fn turbulence(&self, p: Vec3) -> f64 {
+		let mut sum = 0.0;
+		let mut amplitude = 1.0;
+		let mut sample_point = p;
+
+		for _ in 0..self.settings.octaves {
+			sum += amplitude * self.noise.get([sample_point.x, sample_point.y, sample_point.z]).abs();
+			amplitude *= self.settings.gain;
+			sample_point *= self.settings.lacunarity;
+		}
+
+		sum
+	}
+
+	// Musgrave's ridged multifractal, each octave weighted by the previous one
+	fn ridged(&self, p: Vec3) -> f64 {
+		let mut sum = 0.0;
+		let mut amplitude = 1.0;
+		let mut total_amplitude = 0.0;
+		let mut weight = 1.0;
+		let mut sample_point = p;
+
+		for _ in 0..self.settings.octaves {
+			let mut signal = 1.0 - self.noise.get([sample_point.x, sample_point.y, sample_point.z]).abs();
+			signal = signal * signal * weight;
+			weight = (2.0 * signal).clamp(0.0, 1.0);
+
+			sum += amplitude * signal;
+			total_amplitude += amplitude;
+			amplitude *= self.settings.gain;
+			sample_point *= self.settings.lacunarity;
+		}
+
+		if total_amplitude > 0.0 { sum / total_amplitude } else { 0.0 }
+	}
+
+	// Distance to the closest feature point and a random value identifying its cell
+	fn worley(&self, p: Vec3) -> (f64, f64) {
+		let cell = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+
+		let mut closest_sq = f64::MAX;
+		let mut closest_id = 0.0;
+
+		for dz in -1..=1 {
+			for dy in -1..=1 {
+				for dx in -1..=1 {
+					let neighbour = (cell.0 + dx, cell.1 + dy, cell.2 + dz);
+					let hash = self.hash_cell(neighbour);
+
+					let feature = Vec3::new(
+						neighbour.0 as f64 + unit_from_bits(hash),
+						neighbour.1 as f64 + unit_from_bits(hash >> 21),
+						neighbour.2 as f64 + unit_from_bits(hash >> 42)
+					);
+
+					let distance_sq = (feature - p).length_squared();
+					if distance_sq < closest_sq {
+						closest_sq = distance_sq;
+						closest_id = unit_from_bits(hash.rotate_left(11) ^ hash);
+					}
+				}
+			}
+		}
+
+		(f64::sqrt(closest_sq), closest_id)
+	}
+
+	fn hash_cell(&self, cell: (i64, i64, i64)) -> u64 {
+		// splitmix64 finalizer over the combined coordinates
+		let mut h = (cell.0 as u64).wrapping_mul(0x9E3779B97F4A7C15)
+			^ (cell.1 as u64).wrapping_mul(0xC2B2AE3D27D4EB4F)
+			^ (cell.2 as u64).wrapping_mul(0x165667B19E3779F9)
+			^ (self.settings.seed as u64);
+		h ^= h >> 30;
+		h = h.wrapping_mul(0xBF58476D1CE4E5B9);
+		h ^= h >> 27;
+		h = h.wrapping_mul(0x94D049BB133111EB);
+		h ^ (h >> 31)
+	}
+}
+
+impl Texture for ProceduralTexture {
+	fn value_at(&self, _u: f64, _v: f64, p: Vec3, _normal: Vec3) -> Vec3 {
+		self.ramp.sample(self.scalar_at(p))
+	}
+}
+
+// Low 21 bits as a number in [0, 1)
+fn unit_from_bits(bits: u64) -> f64 {
+	(bits & 0x1F_FFFF) as f64 / (1u64 << 21) as f64
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ramp_sorts_and_drops_non_finite_stops() {
+		let white = Vec3::new(1.0, 1.0, 1.0);
+		let ramp = ColorRamp::new(vec![
+			(1.0, white),
+			(f64::NAN, Vec3::new(1.0, 0.0, 0.0)),
+			(0.0, Vec3::ZERO),
+			(f64::INFINITY, Vec3::new(0.0, 1.0, 0.0))
+		]);
+
+		assert_eq!(ramp.sample(-1.0).x, 0.0);
+		assert_eq!(ramp.sample(0.5).y, 0.5);
+		assert_eq!(ramp.sample(2.0).z, 1.0);
+	}
+}
@@ -0,0 +1,309 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+use crate::ONB::OrthonormalBasis;
+use crate::texture::texture::{Texture, UVDifferentials};
+use crate::util::vec3::Vec3;
+
+// Wrappers computing (u, v) from the hit position instead of the surface parameterization,
+// for meshes and boxes whose own coordinates are stretched or missing.
+// The inner texture sees the projected coordinates, the original (u, v) are ignored.
+// Filtered lookups pass on the footprint in projected coordinates, found from the change in position.
+
+// Footprint for a projection, `derivative` gives the change in (u, v) for a small change in position
+fn projected_footprint(differentials: UVDifferentials, derivative: impl Fn(Vec3) -> (f64, f64)) -> UVDifferentials {
+	let (du_dx, dv_dx) = derivative(differentials.dp_dx);
+	let (du_dy, dv_dy) = derivative(differentials.dp_dy);
+	UVDifferentials { du_dx, dv_dx, du_dy, dv_dy, ..differentials }
+}
+
+// Blends three axis aligned planar projections weighted by the normal
+pub struct TriplanarTexture {
+	texture: Arc<dyn Texture>,
+	// world units per texture repeat
+	scale: f64,
+	// higher values narrow the blend region between projections
+	sharpness: f64
+}
+
+impl TriplanarTexture {
+	pub fn new(texture: Arc<dyn Texture>, scale: f64, sharpness: f64) -> Self {
+		TriplanarTexture { texture, scale, sharpness }
+	}
+
+	fn weights(&self, normal: Vec3) -> Vec3 {
+		let weights = Vec3::new(
+			normal.x.abs().powf(self.sharpness),
+			normal.y.abs().powf(self.sharpness),
+			normal.z.abs().powf(self.sharpness)
+		);
+
+		let total = weights.x + weights.y + weights.z;
+		// no normal available, fall back to an even blend
+		if total <= 0.0 { return Vec3::new(1.0, 1.0, 1.0) / 3.0; }
+
+		weights / total
+	}
+}
+
+impl Texture for TriplanarTexture {
+	fn value_at(&self, _u: f64, _v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		let weights = self.weights(normal);
+		let q = p / self.scale;
+
+		let mut color = Vec3::ZERO;
+		if weights.x > 0.0 { color += weights.x * self.texture.value_at(q.z, q.y, p, normal); }
+		if weights.y > 0.0 { color += weights.y * self.texture.value_at(q.x, q.z, p, normal); }
+		if weights.z > 0.0 { color += weights.z * self.texture.value_at(q.x, q.y, p, normal); }
+
+		color
+	}
+
+	fn filtered_value_at(&self, _u: f64, _v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		let weights = self.weights(normal);
+		let q = p / self.scale;
+		let footprint = |derivative: fn(Vec3) -> (f64, f64)| {
+			projected_footprint(differentials, |dp| derivative(dp / self.scale))
+		};
+
+		let mut color = Vec3::ZERO;
+		if weights.x > 0.0 {
+			color += weights.x * self.texture.filtered_value_at(q.z, q.y, p, normal, footprint(|d| (d.z, d.y)));
+		}
+		if weights.y > 0.0 {
+			color += weights.y * self.texture.filtered_value_at(q.x, q.z, p, normal, footprint(|d| (d.x, d.z)));
+		}
+		if weights.z > 0.0 {
+			color += weights.z * self.texture.filtered_value_at(q.x, q.y, p, normal, footprint(|d| (d.x, d.y)));
+		}
+
+		color
+	}
+}
+
+// Projects along the plane normal, one repeat per length of each axis
+pub struct PlanarProjectionTexture {
+	texture: Arc<dyn Texture>,
+	origin: Vec3,
+	u_axis: Vec3,
+	v_axis: Vec3
+}
+
+impl PlanarProjectionTexture {
+	pub fn new(texture: Arc<dyn Texture>, origin: Vec3, u_axis: Vec3, v_axis: Vec3) -> Self {
+		PlanarProjectionTexture { texture, origin, u_axis, v_axis }
+	}
+
+	// Linear, so offsets and small changes in position map the same way
+	fn project(&self, offset: Vec3) -> (f64, f64) {
+		(
+			offset.dot(self.u_axis) / self.u_axis.length_squared(),
+			offset.dot(self.v_axis) / self.v_axis.length_squared()
+		)
+	}
+}
+
+impl Texture for PlanarProjectionTexture {
+	fn value_at(&self, _u: f64, _v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		let (u, v) = self.project(p - self.origin);
+		self.texture.value_at(u, v, p, normal)
+	}
+
+	fn filtered_value_at(&self, _u: f64, _v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		let (u, v) = self.project(p - self.origin);
+		let differentials = projected_footprint(differentials, |dp| self.project(dp));
+		self.texture.filtered_value_at(u, v, p, normal, differentials)
+	}
+}
+
+// u wraps once around the axis, v covers `height` units along it
+pub struct CylindricalProjectionTexture {
+	texture: Arc<dyn Texture>,
+	center: Vec3,
+	basis: OrthonormalBasis,
+	height: f64
+}
+
+impl CylindricalProjectionTexture {
+	pub fn new(texture: Arc<dyn Texture>, center: Vec3, axis: Vec3, height: f64) -> Self {
+		CylindricalProjectionTexture { texture, center, basis: OrthonormalBasis::new(axis), height }
+	}
+
+	fn project(&self, offset: Vec3) -> (f64, f64) {
+		let angle = offset.dot(self.basis.v).atan2(offset.dot(self.basis.u)) + PI;
+		(angle / (2.0 * PI), offset.dot(self.basis.w) / self.height)
+	}
+
+	// Change in (u, v) for a small step `dp` away from `offset`
+	fn project_derivative(&self, offset: Vec3, dp: Vec3) -> (f64, f64) {
+		let (x, y) = (offset.dot(self.basis.u), offset.dot(self.basis.v));
+		let (dx, dy) = (dp.dot(self.basis.u), dp.dot(self.basis.v));
+		let radius_sq = x * x + y * y;
+
+		// the angle is undefined on the axis itself
+		let d_angle = if radius_sq > 0.0 { (x * dy - y * dx) / radius_sq } else { 0.0 };
+		(d_angle / (2.0 * PI), dp.dot(self.basis.w) / self.height)
+	}
+}
+
+impl Texture for CylindricalProjectionTexture {
+	fn value_at(&self, _u: f64, _v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		let (u, v) = self.project(p - self.center);
+		self.texture.value_at(u, v, p, normal)
+	}
+
+	fn filtered_value_at(&self, _u: f64, _v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		let offset = p - self.center;
+		let (u, v) = self.project(offset);
+		let differentials = projected_footprint(differentials, |dp| self.project_derivative(offset, dp));
+		self.texture.filtered_value_at(u, v, p, normal, differentials)
+	}
+}
+
+// Same mapping as Sphere, taken from the direction to the center
+pub struct SphericalProjectionTexture {
+	texture: Arc<dyn Texture>,
+	center: Vec3
+}
+
+impl SphericalProjectionTexture {
+	pub fn new(texture: Arc<dyn Texture>, center: Vec3) -> Self {
+		SphericalProjectionTexture { texture, center }
+	}
+
+	fn project(offset: Vec3) -> (f64, f64) {
+		let direction = offset.unit();
+		let theta = (-direction.y).acos();
+		let phi = (-direction.z).atan2(direction.x) + PI;
+
+		(phi / (2.0 * PI), theta / PI)
+	}
+
+	// Change in (u, v) for a small step `dp` away from `offset`
+	fn project_derivative(offset: Vec3, dp: Vec3) -> (f64, f64) {
+		let rho_sq = offset.x * offset.x + offset.z * offset.z;
+		let rho = rho_sq.sqrt();
+		// the angle around the poles is undefined
+		if rho <= 0.0 { return (0.0, 0.0); }
+
+		// phi = atan2(-z, x) and theta = atan2(rho, -y)
+		let d_phi = (offset.z * dp.x - offset.x * dp.z) / rho_sq;
+		let d_rho = (offset.x * dp.x + offset.z * dp.z) / rho;
+		let d_theta = (rho * dp.y - offset.y * d_rho) / offset.length_squared();
+
+		(d_phi / (2.0 * PI), d_theta / PI)
+	}
+}
+
+impl Texture for SphericalProjectionTexture {
+	fn value_at(&self, _u: f64, _v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		let offset = p - self.center;
+		if offset.is_near_zero() { return self.texture.value_at(0.0, 0.0, p, normal); }
+
+		let (u, v) = Self::project(offset);
+		self.texture.value_at(u, v, p, normal)
+	}
+
+	fn filtered_value_at(&self, _u: f64, _v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		let offset = p - self.center;
+		if offset.is_near_zero() { return self.texture.value_at(0.0, 0.0, p, normal); }
+
+		let (u, v) = Self::project(offset);
+		let differentials = projected_footprint(differentials, |dp| Self::project_derivative(offset, dp));
+		self.texture.filtered_value_at(u, v, p, normal, differentials)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Shows what a projection hands on: the coordinates, or the footprint along x when filtered
+	struct Probe;
+
+	impl Texture for Probe {
+		fn value_at(&self, u: f64, v: f64, _p: Vec3, _normal: Vec3) -> Vec3 {
+			Vec3::new(u, v, 0.0)
+		}
+
+		fn filtered_value_at(&self, _u: f64, _v: f64, _p: Vec3, _normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+			Vec3::new(differentials.du_dx, differentials.dv_dx, differentials.du_dy)
+		}
+	}
+
+	fn assert_close((a_u, a_v): (f64, f64), (b_u, b_v): (f64, f64)) {
+		assert!((a_u - b_u).abs() < 1e-6 && (a_v - b_v).abs() < 1e-6, "{:?} != {:?}", (a_u, a_v), (b_u, b_v));
+	}
+
+	// Central difference of a projection along `dp`
+	fn numeric_derivative(project: impl Fn(Vec3) -> (f64, f64), offset: Vec3, dp: Vec3) -> (f64, f64) {
+		const H: f64 = 1e-6;
+		let (u_0, v_0) = project(offset - H * dp);
+		let (u_1, v_1) = project(offset + H * dp);
+		((u_1 - u_0) / (2.0 * H), (v_1 - v_0) / (2.0 * H))
+	}
+
+	const OFFSETS: [Vec3; 3] = [
+		Vec3 { x: 1.0, y: 0.5, z: 0.3 },
+		Vec3 { x: -0.4, y: -1.2, z: 0.8 },
+		Vec3 { x: 0.2, y: 2.0, z: -1.5 }
+	];
+	const STEPS: [Vec3; 3] = [
+		Vec3 { x: 1.0, y: 0.0, z: 0.0 },
+		Vec3 { x: 0.3, y: -0.7, z: 0.2 },
+		Vec3 { x: 0.0, y: 0.4, z: 1.0 }
+	];
+
+	#[test]
+	fn cylindrical_derivative() {
+		let texture = CylindricalProjectionTexture::new(Arc::new(Probe), Vec3::ZERO, Vec3::new(0.2, 1.0, -0.3), 2.0);
+		for offset in OFFSETS {
+			for dp in STEPS {
+				assert_close(texture.project_derivative(offset, dp), numeric_derivative(|q| texture.project(q), offset, dp));
+			}
+		}
+	}
+
+	#[test]
+	fn spherical_derivative() {
+		for offset in OFFSETS {
+			for dp in STEPS {
+				assert_close(
+					SphericalProjectionTexture::project_derivative(offset, dp),
+					numeric_derivative(SphericalProjectionTexture::project, offset, dp)
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn planar_forwards_footprint() {
+		let texture = PlanarProjectionTexture::new(Arc::new(Probe), Vec3::new(1.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+		let p = Vec3::new(2.0, 5.0, 0.25);
+
+		let uv = texture.value_at(0.0, 0.0, p, Vec3::ZERO);
+		assert_close((uv.x, uv.y), (0.5, 0.25));
+
+		let differentials = UVDifferentials {
+			dp_dx: Vec3::new(0.1, 3.0, 0.2),
+			dp_dy: Vec3::new(0.4, 0.0, 0.0),
+			..UVDifferentials::ZERO
+		};
+		let footprint = texture.filtered_value_at(0.0, 0.0, p, Vec3::ZERO, differentials);
+		assert!((footprint - Vec3::new(0.05, 0.2, 0.2)).length() < 1e-12);
+	}
+
+	#[test]
+	fn triplanar_weights_and_footprint() {
+		let texture = TriplanarTexture::new(Arc::new(Probe), 2.0, 4.0);
+
+		let weights = texture.weights(Vec3::new(0.6, 0.0, 0.8));
+		assert!((weights.x + weights.y + weights.z - 1.0).abs() < 1e-12);
+		assert!(weights.z > weights.x && weights.y == 0.0);
+		assert!((texture.weights(Vec3::ZERO) - Vec3::new(1.0, 1.0, 1.0) / 3.0).length() < 1e-12);
+
+		// facing +y only the xz projection is used
+		let differentials = UVDifferentials { dp_dx: Vec3::new(1.0, 7.0, 0.5), ..UVDifferentials::ZERO };
+		let footprint = texture.filtered_value_at(0.0, 0.0, Vec3::ZERO, Vec3::new(0.0, 1.0, 0.0), differentials);
+		assert!((footprint - Vec3::new(0.5, 0.25, 0.0)).length() < 1e-12);
+	}
+}
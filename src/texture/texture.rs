@@ -0,0 +1,289 @@
+use fastrand::f64;
+use noise::{NoiseFn, Perlin};
+use std::rc::Rc;
+use std::sync::Arc;
+use crate::util::util::deg_to_rad;
+use crate::util::vec3::Vec3;
+
+pub trait Texture: Send + Sync {
+	fn value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3;
+
+	// Lookup averaged over the pixel footprint, textures without prefiltering point sample
+	fn filtered_value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		let _ = differentials;
+		self.value_at(u, v, p, normal)
+	}
+}
+
+// Change in (u, v) and in the hit position between neighbouring pixels, zero when unknown.
+// Also carries the vertex color of mesh hits, which textures cannot find from (u, v) and position.
+#[derive(Debug, Copy, Clone)]
+pub struct UVDifferentials {
+	pub du_dx: f64,
+	pub dv_dx: f64,
+	pub du_dy: f64,
+	pub dv_dy: f64,
+	// for textures looked up by position rather than (u, v)
+	pub dp_dx: Vec3,
+	pub dp_dy: Vec3,
+	// interpolated from the vertices of meshes that store colors, see VertexColorTexture
+	pub vertex_color: Option<Vec3>
+}
+
+impl UVDifferentials {
+	pub const ZERO: UVDifferentials = UVDifferentials {
+		du_dx: 0.0,
+		dv_dx: 0.0,
+		du_dy: 0.0,
+		dv_dy: 0.0,
+		dp_dx: Vec3::ZERO,
+		dp_dy: Vec3::ZERO,
+		vertex_color: None
+	};
+}
+
+// Scale, rotation (degrees, counter-clockwise) and offset applied to (u, v) in that order
+#[derive(Debug, Copy, Clone)]
+pub struct UVTransform {
+	scale: (f64, f64),
+	offset: (f64, f64),
+	sin_rotation: f64,
+	cos_rotation: f64
+}
+
+impl UVTransform {
+	pub fn new(scale: (f64, f64), rotation: f64, offset: (f64, f64)) -> Self {
+		let (sin_rotation, cos_rotation) = deg_to_rad(rotation).sin_cos();
+		UVTransform { scale, offset, sin_rotation, cos_rotation }
+	}
+
+	pub fn point(&self, u: f64, v: f64) -> (f64, f64) {
+		let (u, v) = self.direction(u, v);
+		(u + self.offset.0, v + self.offset.1)
+	}
+
+	// the transform is affine, so footprint vectors only go through its linear part
+	pub fn direction(&self, du: f64, dv: f64) -> (f64, f64) {
+		let du_scaled = du * self.scale.0;
+		let dv_scaled = dv * self.scale.1;
+
+		(
+			self.cos_rotation * du_scaled - self.sin_rotation * dv_scaled,
+			self.sin_rotation * du_scaled + self.cos_rotation * dv_scaled
+		)
+	}
+}
+
+// Fraction of [x_0, x_1] lying on odd cells of a checkerboard with unit cells, the box filtered
+// version of one axis of a checker pattern. Point samples when the interval is empty.
+pub(crate) fn odd_cell_fraction(x_0: f64, x_1: f64) -> f64 {
+	// integral of the square wave that is 0 on even cells and 1 on odd ones, from 0 to x
+	let integral = |x: f64| {
+		let half = x / 2.0;
+		half.floor() + 2.0 * f64::max(half - half.floor() - 0.5, 0.0)
+	};
+
+	if x_1 - x_0 <= 0.0 {
+		return (x_0.floor().rem_euclid(2.0) == 1.0) as u8 as f64;
+	}
+	(integral(x_1) - integral(x_0)) / (x_1 - x_0)
+}
+
+// Odd fraction of two independent checker axes combined, a cell is odd when exactly one of them is
+pub(crate) fn xor_fraction(a: f64, b: f64) -> f64 {
+	a + b - 2.0 * a * b
+}
+
+pub struct SolidColorTexture {
+	albedo: Vec3
+}
+
+impl SolidColorTexture {
+	pub fn new(albedo: Vec3) -> Self {
+		SolidColorTexture { albedo }
+	}
+
+	pub fn from_rgb(red: f64, green: f64, blue: f64) -> Self {
+		SolidColorTexture { albedo: Vec3::new(red, green, blue) }
+	}
+}
+
+impl Texture for SolidColorTexture {
+	fn value_at(&self, u: f64, v: f64, p: Vec3, _normal: Vec3) -> Vec3 {
+		self.albedo
+	}
+}
+
+// Vertex color of mesh hits, e.g. from a scanned PLY. Only filtered lookups see the color,
+// hits without one and plain value_at lookups get the fallback.
+pub struct VertexColorTexture {
+	fallback: Vec3
+}
+
+impl VertexColorTexture {
+	pub fn new(fallback: Vec3) -> Self {
+		VertexColorTexture { fallback }
+	}
+}
+
+impl Texture for VertexColorTexture {
+	fn value_at(&self, _u: f64, _v: f64, _p: Vec3, _normal: Vec3) -> Vec3 {
+		self.fallback
+	}
+
+	fn filtered_value_at(&self, _u: f64, _v: f64, _p: Vec3, _normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		differentials.vertex_color.unwrap_or(self.fallback)
+	}
+}
+
+pub struct CheckeredTexture {
+	scale_inv: f64,
+	even_texture: Arc<dyn Texture>,
+	odd_texture: Arc<dyn Texture>
+}
+
+impl CheckeredTexture {
+	pub fn new(scale: f64, even_texture: Arc<dyn Texture>, odd_texture: Arc<dyn Texture>) -> Self {
+		CheckeredTexture {
+			scale_inv: 1.0 / scale,
+			even_texture,
+			odd_texture
+		}
+	}
+
+	pub fn from_colors(scale: f64, even_color: Vec3, odd_color: Vec3) -> Self {
+		CheckeredTexture {
+			scale_inv: 1.0 / scale,
+			even_texture: Arc::new(SolidColorTexture::new(even_color)),
+			odd_texture: Arc::new(SolidColorTexture::new(odd_color))
+		}
+	}
+}
+
+impl Texture for CheckeredTexture {
+	fn value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3) -> Vec3 {
+		let x_int = f64::floor(self.scale_inv * p.x) as i32;
+		let y_int = f64::floor(self.scale_inv * p.y) as i32;
+		let z_int = f64::floor(self.scale_inv * p.z) as i32;
+
+		let is_even = (x_int + y_int + z_int) % 2 == 0;
+
+		if is_even {
+			self.even_texture.value_at(u, v, p, normal)
+		} else {
+			self.odd_texture.value_at(u, v, p, normal)
+		}
+	}
+
+	// Box filters the cells over the world space footprint, so distant checks fade to grey instead of aliasing
+	fn filtered_value_at(&self, u: f64, v: f64, p: Vec3, normal: Vec3, differentials: UVDifferentials) -> Vec3 {
+		let (dp_dx, dp_dy) = (differentials.dp_dx, differentials.dp_dy);
+		let odd_fraction = |center: f64, d_x: f64, d_y: f64| {
+			let (center, width) = (self.scale_inv * center, self.scale_inv * (d_x.abs() + d_y.abs()));
+			odd_cell_fraction(center - width / 2.0, center + width / 2.0)
+		};
+
+		let odd = xor_fraction(
+			xor_fraction(odd_fraction(p.x, dp_dx.x, dp_dy.x), odd_fraction(p.y, dp_dx.y, dp_dy.y)),
+			odd_fraction(p.z, dp_dx.z, dp_dy.z)
+		);
+
+		let even_color = self.even_texture.filtered_value_at(u, v, p, normal, differentials);
+		let odd_color = self.odd_texture.filtered_value_at(u, v, p, normal, differentials);
+
+		(1.0 - odd) * even_color + odd * odd_color
+	}
+}
+
+pub struct NoiseTexture {
+	scale: f64,
+	noise: Perlin
+}
+
+impl NoiseTexture {
+	pub fn new(scale: f64) -> Self {
+		NoiseTexture { scale, noise: Perlin::default() }
+	}
+
+	pub fn turbulence(&self, p: Vec3, depth: u32) -> f64 {
+		let mut accum = 0.0;
+		let mut sample_point = p;
+		let mut weight = 1.0;
+
+		for i in 0..depth {
+			accum += weight * self.noise.get([
+				sample_point.x,
+				sample_point.y,
+				sample_point.z
+			]);
+			weight /= 2.0;
+			sample_point *= 2.0;
+		}
+
+		accum.abs()
+	}
+}
+
+impl Texture for NoiseTexture {
+	fn value_at(&self, u: f64, v: f64, p: Vec3, _normal: Vec3) -> Vec3 {
+		Vec3::new(0.5, 0.5, 0.5)
+			* (1.0 + (self.scale * p.z + 10.0 * self.turbulence(p, 7)).sin())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn odd_cell_fraction_of_whole_cells() {
+		assert_eq!(odd_cell_fraction(0.0, 1.0), 0.0);
+		assert_eq!(odd_cell_fraction(1.0, 2.0), 1.0);
+		assert_eq!(odd_cell_fraction(-1.0, 0.0), 1.0);
+		assert_eq!(odd_cell_fraction(0.0, 2.0), 0.5);
+		assert_eq!(odd_cell_fraction(0.5, 1.5), 0.5);
+		assert_eq!(odd_cell_fraction(-3.0, 7.0), 0.5);
+	}
+
+	#[test]
+	fn odd_cell_fraction_matches_sampling() {
+		for (x_0, x_1) in [(0.2, 0.9), (0.7, 1.4), (-2.3, 0.6), (3.1, 8.75), (-0.4, -0.1)] {
+			const SAMPLES: usize = 100_000;
+			let odd = (0..SAMPLES)
+				.map(|i| x_0 + (x_1 - x_0) * (i as f64 + 0.5) / SAMPLES as f64)
+				.filter(|x| x.floor().rem_euclid(2.0) == 1.0)
+				.count();
+			assert!((odd_cell_fraction(x_0, x_1) - odd as f64 / SAMPLES as f64).abs() < 1e-4, "[{}, {}]", x_0, x_1);
+		}
+	}
+
+	#[test]
+	fn odd_cell_fraction_of_points() {
+		assert_eq!(odd_cell_fraction(1.3, 1.3), 1.0);
+		assert_eq!(odd_cell_fraction(2.3, 2.3), 0.0);
+		assert_eq!(odd_cell_fraction(-0.5, -0.5), 1.0);
+	}
+
+	#[test]
+	fn checker_filtering() {
+		let checker = CheckeredTexture::from_colors(0.5, Vec3::new(1.0, 1.0, 1.0), Vec3::ZERO);
+		let value = |p: Vec3, width: f64| {
+			let differentials = UVDifferentials {
+				dp_dx: Vec3::new(width, 0.0, 0.0),
+				dp_dy: Vec3::new(0.0, 0.0, width),
+				..UVDifferentials::ZERO
+			};
+			checker.filtered_value_at(0.0, 0.0, p, Vec3::ZERO, differentials).x
+		};
+
+		// without a footprint it matches the point lookup, negative cells included
+		for p in [Vec3::new(0.1, 0.2, 0.3), Vec3::new(-0.3, 0.2, 0.7), Vec3::new(1.2, -0.6, -0.1)] {
+			assert_eq!(value(p, 0.0), checker.value_at(0.0, 0.0, p, Vec3::ZERO).x);
+		}
+
+		// a footprint within one cell keeps its color, one spanning many cells averages
+		assert_eq!(value(Vec3::new(0.25, 0.1, 0.25), 0.2), 1.0);
+		assert!((value(Vec3::new(0.25, 0.1, 0.25), 20.0) - 0.5).abs() < 1e-9);
+		assert!((value(Vec3::new(0.5, 0.1, 0.25), 0.5) - 0.5).abs() < 1e-9);
+	}
+}
@@ -0,0 +1,269 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::sync::Arc;
+use crate::AABB::AABB;
+use crate::texture::texture::Texture;
+use crate::util::vec3::Vec3;
+
+// Element type of headerless voxel files
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum VoxelFormat {
+	// 8 bit unsigned, mapped to [0, 1]
+	U8,
+	// 32 bit little endian float, used as stored
+	F32
+}
+
+// Dense 3D grid of values with one or more channels, stretched over a box in world space.
+// Voxel centers sit at the middle of their cells, x varies fastest, then y, then z.
+pub struct VoxelGrid {
+	data: Vec<f32>,
+	channels: usize,
+	resolution: (usize, usize, usize),
+	bounds: AABB,
+	// largest channel average over all voxels, an upper bound of the interpolated scalar
+	max_scalar: f64
+}
+
+impl VoxelGrid {
+	pub fn new(data: Vec<f32>, channels: usize, resolution: (usize, usize, usize), bounds: AABB) -> Self {
+		assert!(channels == 1 || channels == 3, "voxel grids have 1 or 3 channels");
+		assert!(resolution.0 > 0 && resolution.1 > 0 && resolution.2 > 0, "voxel grids need at least one voxel per axis");
+		assert_eq!(data.len(), resolution.0 * resolution.1 * resolution.2 * channels, "voxel data does not match the resolution");
+
+		let max_scalar = data.chunks(channels)
+			.map(|voxel| voxel.iter().sum::<f32>() as f64 / channels as f64)
+			.fold(0.0, f64::max);
+
+		VoxelGrid { data, channels, resolution, bounds, max_scalar }
+	}
+
+	// Reads a headerless file holding the voxels in grid order
+	pub fn load_raw<P: AsRef<Path>>(
+		filepath: P,
+		format: VoxelFormat,
+		channels: usize,
+		resolution: (usize, usize, usize),
+		bounds: AABB
+	) -> Result<Self, Error> {
+		// the file is user supplied, reject what VoxelGrid::new would panic on
+		if channels != 1 && channels != 3 {
+			return Err(Error::new(ErrorKind::InvalidData, format!("voxel grids have 1 or 3 channels, not {}", channels)));
+		}
+		if resolution.0 == 0 || resolution.1 == 0 || resolution.2 == 0 {
+			return Err(Error::new(
+				ErrorKind::InvalidData,
+				format!("resolution {}x{}x{} has no voxels", resolution.0, resolution.1, resolution.2)
+			));
+		}
+
+		let bytes_per_value = match format {
+			VoxelFormat::U8 => 1,
+			VoxelFormat::F32 => 4
+		};
+		let expected_bytes = resolution.0.checked_mul(resolution.1)
+			.and_then(|size| size.checked_mul(resolution.2))
+			.and_then(|size| size.checked_mul(channels * bytes_per_value))
+			.ok_or_else(|| Error::new(ErrorKind::InvalidData, "voxel grid resolution is too large"))?;
+
+		let bytes = fs::read(filepath)?;
+
+		if bytes.len() != expected_bytes {
+			return Err(Error::new(
+				ErrorKind::InvalidData,
+				format!(
+					"expected {}x{}x{}x{} voxels ({} bytes), file holds {} bytes",
+					resolution.0, resolution.1, resolution.2, channels, expected_bytes, bytes.len()
+				)
+			));
+		}
+
+		let data: Vec<f32> = match format {
+			VoxelFormat::U8 => bytes.iter().map(|&byte| byte as f32 / 255.0).collect(),
+			VoxelFormat::F32 => bytes.chunks_exact(4)
+				.map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+				.collect()
+		};
+
+		Ok(Self::new(data, channels, resolution, bounds))
+	}
+
+	// Evaluates a function at every voxel center
+	pub fn from_fn(resolution: (usize, usize, usize), bounds: AABB, f: impl Fn(Vec3) -> Vec3) -> Self {
+		let mut data = Vec::with_capacity(resolution.0 * resolution.1 * resolution.2 * 3);
+
+		for k in 0..resolution.2 {
+			for j in 0..resolution.1 {
+				for i in 0..resolution.0 {
+					let value = f(Self::voxel_center(resolution, bounds, i, j, k));
+					data.extend([value.x as f32, value.y as f32, value.z as f32]);
+				}
+			}
+		}
+
+		Self::new(data, 3, resolution, bounds)
+	}
+
+	// Single channel grid, e.g. a density field
+	pub fn from_scalar_fn(resolution: (usize, usize, usize), bounds: AABB, f: impl Fn(Vec3) -> f64) -> Self {
+		let mut data = Vec::with_capacity(resolution.0 * resolution.1 * resolution.2);
+
+		for k in 0..resolution.2 {
+			for j in 0..resolution.1 {
+				for i in 0..resolution.0 {
+					data.push(f(Self::voxel_center(resolution, bounds, i, j, k)) as f32);
+				}
+			}
+		}
+
+		Self::new(data, 1, resolution, bounds)
+	}
+
+	// Bakes a solid texture (noise, procedural patterns) so it can be looked up cheaply
+	pub fn from_texture(resolution: (usize, usize, usize), bounds: AABB, texture: &dyn Texture) -> Self {
+		Self::from_fn(resolution, bounds, |p| texture.value_at(0.0, 0.0, p, Vec3::ZERO))
+	}
+
+	fn voxel_center(resolution: (usize, usize, usize), bounds: AABB, i: usize, j: usize, k: usize) -> Vec3 {
+		Vec3::new(
+			bounds.x.min + (i as f64 + 0.5) / resolution.0 as f64 * bounds.x.size(),
+			bounds.y.min + (j as f64 + 0.5) / resolution.1 as f64 * bounds.y.size(),
+			bounds.z.min + (k as f64 + 0.5) / resolution.2 as f64 * bounds.z.size()
+		)
+	}
+
+	pub fn bounds(&self) -> AABB {
+		self.bounds
+	}
+
+	pub fn max_scalar(&self) -> f64 {
+		self.max_scalar
+	}
+
+	fn voxel(&self, i: usize, j: usize, k: usize) -> Vec3 {
+		let index = ((k * self.resolution.1 + j) * self.resolution.0 + i) * self.channels;
+
+		if self.channels == 1 {
+			let value = self.data[index] as f64;
+			Vec3::new(value, value, value)
+		} else {
+			Vec3::new(self.data[index] as f64, self.data[index + 1] as f64, self.data[index + 2] as f64)
+		}
+	}
+
+	// Trilinear interpolation between voxel centers, zero outside the bounds
+	pub fn sample(&self, p: Vec3) -> Vec3 {
+		if !self.bounds.x.contains(p.x) || !self.bounds.y.contains(p.y) || !self.bounds.z.contains(p.z) {
+			return Vec3::ZERO;
+		}
+
+		// continuous voxel coordinates, clamped so border voxels extend to the bounds
+		let grid_coordinate = |t: f64, interval_min: f64, size: f64, resolution: usize| {
+			let x = ((t - interval_min) / size * resolution as f64 - 0.5).clamp(0.0, (resolution - 1) as f64);
+			let i = usize::min(x.floor() as usize, resolution.saturating_sub(2));
+			(i, usize::min(i + 1, resolution - 1), x - i as f64)
+		};
+
+		let (i_0, i_1, fx) = grid_coordinate(p.x, self.bounds.x.min, self.bounds.x.size(), self.resolution.0);
+		let (j_0, j_1, fy) = grid_coordinate(p.y, self.bounds.y.min, self.bounds.y.size(), self.resolution.1);
+		let (k_0, k_1, fz) = grid_coordinate(p.z, self.bounds.z.min, self.bounds.z.size(), self.resolution.2);
+
+		let lerp = |a: Vec3, b: Vec3, t: f64| (1.0 - t) * a + t * b;
+
+		let c_00 = lerp(self.voxel(i_0, j_0, k_0), self.voxel(i_1, j_0, k_0), fx);
+		let c_10 = lerp(self.voxel(i_0, j_1, k_0), self.voxel(i_1, j_1, k_0), fx);
+		let c_01 = lerp(self.voxel(i_0, j_0, k_1), self.voxel(i_1, j_0, k_1), fx);
+		let c_11 = lerp(self.voxel(i_0, j_1, k_1), self.voxel(i_1, j_1, k_1), fx);
+
+		lerp(lerp(c_00, c_10, fy), lerp(c_01, c_11, fy), fz)
+	}
+
+	// Channel average, used when the grid is a density field
+	pub fn sample_scalar(&self, p: Vec3) -> f64 {
+		let value = self.sample(p);
+		(value.x + value.y + value.z) / 3.0
+	}
+}
+
+// A voxel grid looked up by hit position, usable as a color or density texture
+pub struct VoxelTexture {
+	grid: Arc<VoxelGrid>,
+	scale: f64
+}
+
+impl VoxelTexture {
+	pub fn new(grid: Arc<VoxelGrid>) -> Self {
+		VoxelTexture { grid, scale: 1.0 }
+	}
+
+	// Multiplies every value, e.g. to turn a normalized field into a density
+	pub fn with_scale(grid: Arc<VoxelGrid>, scale: f64) -> Self {
+		VoxelTexture { grid, scale }
+	}
+
+	pub fn grid(&self) -> &Arc<VoxelGrid> {
+		&self.grid
+	}
+
+	// Largest value the texture returns as a scalar, a majorant for tracking through it
+	pub fn max_scalar(&self) -> f64 {
+		self.scale * self.grid.max_scalar()
+	}
+}
+
+impl Texture for VoxelTexture {
+	fn value_at(&self, _u: f64, _v: f64, p: Vec3, _normal: Vec3) -> Vec3 {
+		self.scale * self.grid.sample(p)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::loader::test_file;
+
+	fn unit_box() -> AABB {
+		AABB::from_corners(Vec3::ZERO, Vec3::new(1.0, 1.0, 1.0))
+	}
+
+	fn assert_invalid_data(result: Result<VoxelGrid, Error>) {
+		match result {
+			Err(error) => assert_eq!(error.kind(), ErrorKind::InvalidData),
+			Ok(_) => panic!("loaded an invalid voxel file")
+		}
+	}
+
+	#[test]
+	fn load_u8_and_f32() {
+		let grid = VoxelGrid::load_raw(test_file("u8.raw", &[0, 255]), VoxelFormat::U8, 1, (2, 1, 1), unit_box()).unwrap();
+		assert!((grid.sample_scalar(Vec3::new(0.25, 0.5, 0.5))).abs() < 1e-6);
+		assert!((grid.sample_scalar(Vec3::new(0.5, 0.5, 0.5)) - 0.5).abs() < 1e-6);
+		assert!((grid.max_scalar() - 1.0).abs() < 1e-6);
+
+		let bytes: Vec<u8> = [0.5f32, 2.0, 4.0].iter().flat_map(|value| value.to_le_bytes()).collect();
+		let grid = VoxelGrid::load_raw(test_file("f32.raw", &bytes), VoxelFormat::F32, 3, (1, 1, 1), unit_box()).unwrap();
+		assert!((grid.sample(Vec3::new(0.9, 0.1, 0.5)) - Vec3::new(0.5, 2.0, 4.0)).length() < 1e-6);
+		assert_eq!(grid.sample(Vec3::new(1.5, 0.5, 0.5)).length(), 0.0);
+	}
+
+	#[test]
+	fn load_rejects_bad_shapes() {
+		let path = test_file("shapes.raw", &[0; 6]);
+
+		assert_invalid_data(VoxelGrid::load_raw(&path, VoxelFormat::U8, 2, (3, 1, 1), unit_box()));
+		assert_invalid_data(VoxelGrid::load_raw(&path, VoxelFormat::U8, 1, (6, 0, 1), unit_box()));
+		assert_invalid_data(VoxelGrid::load_raw(&path, VoxelFormat::U8, 1, (usize::MAX, 2, 1), unit_box()));
+		// 6 bytes are neither 2x2x2 voxels nor a whole number of floats
+		assert_invalid_data(VoxelGrid::load_raw(&path, VoxelFormat::U8, 1, (2, 2, 2), unit_box()));
+		assert_invalid_data(VoxelGrid::load_raw(&path, VoxelFormat::F32, 1, (2, 1, 1), unit_box()));
+
+		assert!(VoxelGrid::load_raw(&path, VoxelFormat::U8, 3, (2, 1, 1), unit_box()).is_ok());
+	}
+
+	#[test]
+	fn missing_file() {
+		let result = VoxelGrid::load_raw(std::env::temp_dir().join("grayshift-missing.raw"), VoxelFormat::U8, 1, (1, 1, 1), unit_box());
+		assert!(matches!(result, Err(error) if error.kind() == ErrorKind::NotFound));
+	}
+}
@@ -1,3 +1,4 @@
 pub mod interval;
+pub mod transform;
 pub mod util;
 pub mod vec3;
\ No newline at end of file
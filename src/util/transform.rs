@@ -0,0 +1,567 @@
+use std::ops::Mul;
+use crate::AABB::AABB;
+use crate::util::util::deg_to_rad;
+use crate::util::vec3::Vec3;
+
+// Row major 4x4 matrix applied to column vectors
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Matrix4 {
+	pub m: [[f64; 4]; 4]
+}
+
+impl Matrix4 {
+	pub const IDENTITY: Matrix4 = Matrix4 {
+		m: [
+			[1.0, 0.0, 0.0, 0.0],
+			[0.0, 1.0, 0.0, 0.0],
+			[0.0, 0.0, 1.0, 0.0],
+			[0.0, 0.0, 0.0, 1.0]
+		]
+	};
+
+	pub fn new(m: [[f64; 4]; 4]) -> Self {
+		Matrix4 { m }
+	}
+
+	// Column major storage, as used by glTF and OpenGL
+	pub fn from_columns(columns: [[f64; 4]; 4]) -> Self {
+		Matrix4 { m: columns }.transpose()
+	}
+
+	pub fn transpose(&self) -> Matrix4 {
+		let mut m = [[0.0; 4]; 4];
+		for (row, values) in m.iter_mut().enumerate() {
+			for (column, value) in values.iter_mut().enumerate() {
+				*value = self.m[column][row];
+			}
+		}
+		Matrix4 { m }
+	}
+
+	pub fn determinant(&self) -> f64 {
+		(0..4).map(|column| self.m[0][column] * self.cofactor(0, column)).sum()
+	}
+
+	// None for singular matrices, e.g. a scale of zero along some axis
+	pub fn inverse(&self) -> Option<Matrix4> {
+		let determinant = self.determinant();
+		if determinant.abs() < 1e-12 { return None; }
+
+		// adjugate over the determinant
+		let mut m = [[0.0; 4]; 4];
+		for (row, values) in m.iter_mut().enumerate() {
+			for (column, value) in values.iter_mut().enumerate() {
+				*value = self.cofactor(column, row) / determinant;
+			}
+		}
+		Some(Matrix4 { m })
+	}
+
+	fn cofactor(&self, row: usize, column: usize) -> f64 {
+		let mut minor = [[0.0; 3]; 3];
+		for (minor_row, source_row) in (0..4).filter(|&r| r != row).enumerate() {
+			for (minor_column, source_column) in (0..4).filter(|&c| c != column).enumerate() {
+				minor[minor_row][minor_column] = self.m[source_row][source_column];
+			}
+		}
+
+		let determinant = minor[0][0] * (minor[1][1] * minor[2][2] - minor[1][2] * minor[2][1])
+			- minor[0][1] * (minor[1][0] * minor[2][2] - minor[1][2] * minor[2][0])
+			+ minor[0][2] * (minor[1][0] * minor[2][1] - minor[1][1] * minor[2][0]);
+
+		if (row + column).is_multiple_of(2) { determinant } else { -determinant }
+	}
+
+	pub fn transform_point(&self, p: Vec3) -> Vec3 {
+		self.transform_vector(p) + Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3])
+	}
+
+	// Ignores the translation, for directions and offsets
+	pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+		let m = &self.m;
+		Vec3::new(
+			m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+			m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+			m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z
+		)
+	}
+}
+
+impl Mul for Matrix4 {
+	type Output = Matrix4;
+	fn mul(self, rhs: Matrix4) -> Self::Output {
+		let mut m = [[0.0; 4]; 4];
+		for (row, values) in m.iter_mut().enumerate() {
+			for (column, value) in values.iter_mut().enumerate() {
+				*value = (0..4).map(|k| self.m[row][k] * rhs.m[k][column]).sum();
+			}
+		}
+		Matrix4 { m }
+	}
+}
+
+// Affine transform kept together with its inverse. Compose with `then`, e.g.
+// `Transform::scale(s).then(&Transform::rotate(axis, angle)).then(&Transform::translate(offset))`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+	matrix: Matrix4,
+	inverse: Matrix4,
+	// inverse transpose, kept since every instanced hit transforms its normals
+	normal_matrix: Matrix4
+}
+
+impl Transform {
+	pub const IDENTITY: Transform = Transform {
+		matrix: Matrix4::IDENTITY,
+		inverse: Matrix4::IDENTITY,
+		normal_matrix: Matrix4::IDENTITY
+	};
+
+	fn new(matrix: Matrix4, inverse: Matrix4) -> Self {
+		Transform { matrix, inverse, normal_matrix: inverse.transpose() }
+	}
+
+	// None when the matrix cannot be inverted
+	pub fn from_matrix(matrix: Matrix4) -> Option<Self> {
+		Some(Transform::new(matrix, matrix.inverse()?))
+	}
+
+	pub fn translate(offset: Vec3) -> Self {
+		let matrix = Matrix4::new([
+			[1.0, 0.0, 0.0, offset.x],
+			[0.0, 1.0, 0.0, offset.y],
+			[0.0, 0.0, 1.0, offset.z],
+			[0.0, 0.0, 0.0, 1.0]
+		]);
+		let inverse = Matrix4::new([
+			[1.0, 0.0, 0.0, -offset.x],
+			[0.0, 1.0, 0.0, -offset.y],
+			[0.0, 0.0, 1.0, -offset.z],
+			[0.0, 0.0, 0.0, 1.0]
+		]);
+		Transform::new(matrix, inverse)
+	}
+
+	// Components must be non-zero
+	pub fn scale(factors: Vec3) -> Self {
+		let matrix = Matrix4::new([
+			[factors.x, 0.0, 0.0, 0.0],
+			[0.0, factors.y, 0.0, 0.0],
+			[0.0, 0.0, factors.z, 0.0],
+			[0.0, 0.0, 0.0, 1.0]
+		]);
+		let inverse = Matrix4::new([
+			[1.0 / factors.x, 0.0, 0.0, 0.0],
+			[0.0, 1.0 / factors.y, 0.0, 0.0],
+			[0.0, 0.0, 1.0 / factors.z, 0.0],
+			[0.0, 0.0, 0.0, 1.0]
+		]);
+		Transform::new(matrix, inverse)
+	}
+
+	pub fn uniform_scale(factor: f64) -> Self {
+		Self::scale(Vec3::new(factor, factor, factor))
+	}
+
+	// Counter-clockwise by `angle` degrees looking down the axis towards the origin
+	pub fn rotate(axis: Vec3, angle: f64) -> Self {
+		let axis = axis.unit();
+		let (sin, cos) = deg_to_rad(angle).sin_cos();
+		let (x, y, z) = (axis.x, axis.y, axis.z);
+		let t = 1.0 - cos;
+
+		let matrix = Matrix4::new([
+			[t * x * x + cos, t * x * y - sin * z, t * x * z + sin * y, 0.0],
+			[t * x * y + sin * z, t * y * y + cos, t * y * z - sin * x, 0.0],
+			[t * x * z - sin * y, t * y * z + sin * x, t * z * z + cos, 0.0],
+			[0.0, 0.0, 0.0, 1.0]
+		]);
+
+		// rotations are orthonormal
+		Transform::new(matrix, matrix.transpose())
+	}
+
+	pub fn rotate_x(angle: f64) -> Self {
+		Self::rotate(Vec3::new(1.0, 0.0, 0.0), angle)
+	}
+
+	pub fn rotate_y(angle: f64) -> Self {
+		Self::rotate(Vec3::new(0.0, 1.0, 0.0), angle)
+	}
+
+	pub fn rotate_z(angle: f64) -> Self {
+		Self::rotate(Vec3::new(0.0, 0.0, 1.0), angle)
+	}
+
+	// This transform followed by `next`
+	pub fn then(&self, next: &Transform) -> Transform {
+		Transform::new(next.matrix * self.matrix, self.inverse * next.inverse)
+	}
+
+	pub fn matrix(&self) -> &Matrix4 {
+		&self.matrix
+	}
+
+	// Mirroring transforms turn the winding of triangles around
+	pub fn is_mirroring(&self) -> bool {
+		self.matrix.determinant() < 0.0
+	}
+
+	pub fn point(&self, p: Vec3) -> Vec3 {
+		self.matrix.transform_point(p)
+	}
+
+	pub fn vector(&self, v: Vec3) -> Vec3 {
+		self.matrix.transform_vector(v)
+	}
+
+	// Normals go through the inverse transpose to stay perpendicular to the surface, not normalized
+	pub fn normal(&self, n: Vec3) -> Vec3 {
+		self.normal_matrix.transform_vector(n)
+	}
+
+	pub fn inverse_point(&self, p: Vec3) -> Vec3 {
+		self.inverse.transform_point(p)
+	}
+
+	pub fn inverse_vector(&self, v: Vec3) -> Vec3 {
+		self.inverse.transform_vector(v)
+	}
+
+	// Box around the eight transformed corners
+	pub fn bounding_box(&self, bbox: AABB) -> AABB {
+		let mut result = AABB::EMPTY;
+
+		for i in 0..2 {
+			for j in 0..2 {
+				for k in 0..2 {
+					let corner = Vec3::new(
+						if i == 0 { bbox.x.min } else { bbox.x.max },
+						if j == 0 { bbox.y.min } else { bbox.y.max },
+						if k == 0 { bbox.z.min } else { bbox.z.max }
+					);
+					let p = self.point(corner);
+					result = AABB::from_AABB_pair(result, AABB::from_corners(p, p));
+				}
+			}
+		}
+
+		result
+	}
+}
+
+// Unit quaternion for rotations that interpolate without gimbal issues
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quaternion {
+	pub w: f64,
+	pub x: f64,
+	pub y: f64,
+	pub z: f64
+}
+
+impl Quaternion {
+	pub const IDENTITY: Quaternion = Quaternion { w: 1.0, x: 0.0, y: 0.0, z: 0.0 };
+
+	// Same sense as Transform::rotate, angle in degrees
+	pub fn from_axis_angle(axis: Vec3, angle: f64) -> Self {
+		let axis = axis.unit();
+		let (sin, cos) = (deg_to_rad(angle) / 2.0).sin_cos();
+		Quaternion { w: cos, x: sin * axis.x, y: sin * axis.y, z: sin * axis.z }
+	}
+
+	fn dot(&self, other: &Quaternion) -> f64 {
+		self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+	}
+
+	// Radians turned going to `other` the short way
+	pub fn angle_to(&self, other: &Quaternion) -> f64 {
+		2.0 * self.dot(other).abs().min(1.0).acos()
+	}
+
+	// Spherical interpolation along the shorter arc
+	pub fn slerp(&self, other: &Quaternion, t: f64) -> Quaternion {
+		let mut cos = self.dot(other);
+		let mut target = *other;
+		if cos < 0.0 {
+			cos = -cos;
+			target = Quaternion { w: -other.w, x: -other.x, y: -other.y, z: -other.z };
+		}
+
+		// nearly parallel, a normalized lerp is accurate and avoids dividing by sin(0)
+		let (weight_self, weight_target) = if cos > 0.9995 {
+			(1.0 - t, t)
+		} else {
+			let theta = cos.acos();
+			let sin = theta.sin();
+			(((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+		};
+
+		Quaternion {
+			w: weight_self * self.w + weight_target * target.w,
+			x: weight_self * self.x + weight_target * target.x,
+			y: weight_self * self.y + weight_target * target.y,
+			z: weight_self * self.z + weight_target * target.z
+		}.normalized()
+	}
+
+	fn normalized(&self) -> Quaternion {
+		let length = self.dot(self).sqrt();
+		Quaternion { w: self.w / length, x: self.x / length, y: self.y / length, z: self.z / length }
+	}
+
+	pub fn transform(&self) -> Transform {
+		let Quaternion { w, x, y, z } = *self;
+		let matrix = Matrix4::new([
+			[1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y), 0.0],
+			[2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x), 0.0],
+			[2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y), 0.0],
+			[0.0, 0.0, 0.0, 1.0]
+		]);
+		Transform::new(matrix, matrix.transpose())
+	}
+}
+
+// Pose at one point in time: scaled, then rotated, then translated
+#[derive(Debug, Copy, Clone)]
+pub struct Keyframe {
+	// same units as the camera shutter, 0 to 1 unless changed there
+	pub time: f64,
+	pub translation: Vec3,
+	pub rotation: Quaternion,
+	// components must be non-zero
+	pub scale: Vec3
+}
+
+impl Keyframe {
+	// Identity pose, fill in the rest with struct update syntax
+	pub fn new(time: f64) -> Self {
+		Keyframe { time, translation: Vec3::ZERO, rotation: Quaternion::IDENTITY, scale: Vec3::new(1.0, 1.0, 1.0) }
+	}
+
+	pub fn transform(&self) -> Transform {
+		Transform::scale(self.scale)
+			.then(&self.rotation.transform())
+			.then(&Transform::translate(self.translation))
+	}
+}
+
+// Keyframes interpolated over time, held at the first and last pose outside their range
+pub struct AnimatedTransform {
+	keyframes: Vec<Keyframe>
+}
+
+impl AnimatedTransform {
+	pub fn new(mut keyframes: Vec<Keyframe>) -> Self {
+		assert!(!keyframes.is_empty(), "animations need at least one keyframe");
+		keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+		AnimatedTransform { keyframes }
+	}
+
+	// Linear in translation and scale, spherical in rotation
+	pub fn at(&self, time: f64) -> Transform {
+		let first = &self.keyframes[0];
+		let last = &self.keyframes[self.keyframes.len() - 1];
+		if time <= first.time { return first.transform(); }
+		if time >= last.time { return last.transform(); }
+
+		let next = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+		let (from, to) = (&self.keyframes[next - 1], &self.keyframes[next]);
+		let t = (time - from.time) / (to.time - from.time);
+
+		Keyframe {
+			time,
+			translation: (1.0 - t) * from.translation + t * to.translation,
+			rotation: from.rotation.slerp(&to.rotation, t),
+			scale: (1.0 - t) * from.scale + t * to.scale
+		}.transform()
+	}
+
+	// Box around the object over the whole animation. Poses are sampled along every segment
+	// and the box grows by the furthest a corner can stray from the chord between samples.
+	pub fn bounding_box(&self, bbox: AABB) -> AABB {
+		const STEPS: usize = 16;
+
+		let mut result = self.keyframes[0].transform().bounding_box(bbox);
+
+		for pair in self.keyframes.windows(2) {
+			let (from, to) = (&pair[0], &pair[1]);
+
+			for step in 1..=STEPS {
+				let time = from.time + (to.time - from.time) * step as f64 / STEPS as f64;
+				result = AABB::from_AABB_pair(result, self.at(time).bounding_box(bbox));
+			}
+
+			// sagitta of the arc a corner sweeps between two samples
+			let step_angle = from.rotation.angle_to(&to.rotation) / STEPS as f64;
+			let radius = [from.scale, to.scale].iter()
+				.map(|scale| furthest_corner(bbox, *scale))
+				.fold(0.0, f64::max);
+			result = result.pad(radius * (1.0 - (step_angle / 2.0).cos()));
+		}
+
+		result
+	}
+}
+
+// Furthest corner of the scaled box from the origin, which rotations turn around
+fn furthest_corner(bbox: AABB, scale: Vec3) -> f64 {
+	let x = f64::max(bbox.x.min.abs(), bbox.x.max.abs()) * scale.x.abs();
+	let y = f64::max(bbox.y.min.abs(), bbox.y.max.abs()) * scale.y.abs();
+	let z = f64::max(bbox.z.min.abs(), bbox.z.max.abs()) * scale.z.abs();
+	Vec3::new(x, y, z).length()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_close(a: Vec3, b: Vec3) {
+		assert!((a - b).length() < 1e-9, "{:?} != {:?}", a, b);
+	}
+
+	fn assert_matrix_close(a: &Matrix4, b: &Matrix4) {
+		for row in 0..4 {
+			for column in 0..4 {
+				assert!((a.m[row][column] - b.m[row][column]).abs() < 1e-9, "{:?} != {:?}", a, b);
+			}
+		}
+	}
+
+	#[test]
+	fn inverse_of_general_matrix() {
+		let matrix = Matrix4::new([
+			[2.0, 0.0, 1.0, 3.0],
+			[1.0, 3.0, 0.0, -1.0],
+			[0.0, 1.0, 4.0, 2.0],
+			[0.0, 0.0, 0.0, 1.0]
+		]);
+		let inverse = matrix.inverse().unwrap();
+		assert_matrix_close(&(matrix * inverse), &Matrix4::IDENTITY);
+		assert_matrix_close(&(inverse * matrix), &Matrix4::IDENTITY);
+	}
+
+	#[test]
+	fn determinant_and_singular_matrix() {
+		assert!((Transform::scale(Vec3::new(2.0, 3.0, 4.0)).matrix().determinant() - 24.0).abs() < 1e-9);
+
+		let flat = Matrix4::new([
+			[1.0, 0.0, 0.0, 0.0],
+			[0.0, 1.0, 0.0, 0.0],
+			[0.0, 0.0, 0.0, 0.0],
+			[0.0, 0.0, 0.0, 1.0]
+		]);
+		assert!(flat.inverse().is_none());
+		assert!(Transform::from_matrix(flat).is_none());
+	}
+
+	#[test]
+	fn from_columns_transposes() {
+		let transform = Transform::from_matrix(Matrix4::from_columns([
+			[1.0, 0.0, 0.0, 0.0],
+			[0.0, 1.0, 0.0, 0.0],
+			[0.0, 0.0, 1.0, 0.0],
+			[5.0, 6.0, 7.0, 1.0]
+		])).unwrap();
+		assert_close(transform.point(Vec3::ZERO), Vec3::new(5.0, 6.0, 7.0));
+	}
+
+	#[test]
+	fn then_applies_in_order() {
+		let transform = Transform::scale(Vec3::new(2.0, 2.0, 2.0))
+			.then(&Transform::rotate_z(90.0))
+			.then(&Transform::translate(Vec3::new(0.0, 0.0, 1.0)));
+
+		// scaled to (2, 0, 0), turned onto +y, then lifted
+		assert_close(transform.point(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(0.0, 2.0, 1.0));
+		assert_close(transform.vector(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(0.0, 2.0, 0.0));
+	}
+
+	#[test]
+	fn inverse_round_trips() {
+		let transform = Transform::rotate(Vec3::new(1.0, 1.0, 0.0), 30.0)
+			.then(&Transform::scale(Vec3::new(1.0, 3.0, 0.5)))
+			.then(&Transform::translate(Vec3::new(-2.0, 1.0, 4.0)));
+		let p = Vec3::new(0.3, -1.2, 2.5);
+
+		assert_close(transform.inverse_point(transform.point(p)), p);
+		assert_close(transform.inverse_vector(transform.vector(p)), p);
+		assert_matrix_close(&(transform.matrix * transform.inverse), &Matrix4::IDENTITY);
+	}
+
+	#[test]
+	fn normals_stay_perpendicular() {
+		let transform = Transform::scale(Vec3::new(1.0, 4.0, 1.0)).then(&Transform::rotate_x(20.0));
+		let tangent = Vec3::new(1.0, -1.0, 0.0);
+		let normal = Vec3::new(1.0, 1.0, 0.0);
+
+		assert!(transform.normal(normal).dot(transform.vector(tangent)).abs() < 1e-9);
+	}
+
+	#[test]
+	fn mirroring() {
+		assert!(Transform::scale(Vec3::new(-1.0, 1.0, 1.0)).is_mirroring());
+		assert!(!Transform::rotate_y(120.0).is_mirroring());
+	}
+
+	#[test]
+	fn slerp_endpoints_and_midpoint() {
+		let from = Quaternion::IDENTITY;
+		let to = Quaternion::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), 90.0);
+		let x = Vec3::new(1.0, 0.0, 0.0);
+
+		assert_close(from.slerp(&to, 0.0).transform().vector(x), x);
+		assert_close(from.slerp(&to, 1.0).transform().vector(x), to.transform().vector(x));
+
+		let half = 45.0_f64.to_radians();
+		assert_close(from.slerp(&to, 0.5).transform().vector(x), Vec3::new(half.cos(), 0.0, -half.sin()));
+		assert!((from.angle_to(&from.slerp(&to, 0.25)) - 22.5_f64.to_radians()).abs() < 1e-9);
+	}
+
+	#[test]
+	fn slerp_takes_shorter_arc() {
+		let from = Quaternion::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), 10.0);
+		// 350 degrees is the same rotation as -10, with the quaternion in the other hemisphere
+		let to = Quaternion::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), 350.0);
+		assert!((from.angle_to(&to) - 20.0_f64.to_radians()).abs() < 1e-9);
+
+		let middle = from.slerp(&to, 0.5).transform();
+		assert_close(middle.vector(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(1.0, 0.0, 0.0));
+	}
+
+	#[test]
+	fn quaternion_matches_matrix_rotation() {
+		let axis = Vec3::new(1.0, -2.0, 0.5);
+		let p = Vec3::new(0.3, 0.7, -1.1);
+		assert_close(
+			Quaternion::from_axis_angle(axis, 75.0).transform().vector(p),
+			Transform::rotate(axis, 75.0).vector(p)
+		);
+	}
+
+	#[test]
+	fn animation_holds_and_interpolates() {
+		let animation = AnimatedTransform::new(vec![
+			Keyframe { translation: Vec3::new(4.0, 0.0, 0.0), ..Keyframe::new(1.0) },
+			Keyframe::new(0.0)
+		]);
+
+		assert_close(animation.at(-1.0).point(Vec3::ZERO), Vec3::ZERO);
+		assert_close(animation.at(0.25).point(Vec3::ZERO), Vec3::new(1.0, 0.0, 0.0));
+		assert_close(animation.at(2.0).point(Vec3::ZERO), Vec3::new(4.0, 0.0, 0.0));
+	}
+
+	#[test]
+	fn animated_bounds_cover_sampled_poses() {
+		let animation = AnimatedTransform::new(vec![
+			Keyframe::new(0.0),
+			Keyframe { rotation: Quaternion::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), 170.0), ..Keyframe::new(1.0) }
+		]);
+		let bbox = AABB::from_corners(Vec3::new(1.0, -0.1, -0.1), Vec3::new(2.0, 0.1, 0.1));
+		let bounds = animation.bounding_box(bbox);
+
+		for step in 0..=1000 {
+			let p = animation.at(step as f64 / 1000.0).point(Vec3::new(2.0, 0.0, 0.0));
+			assert!(bounds.x.contains(p.x) && bounds.y.contains(p.y) && bounds.z.contains(p.z), "{:?}", p);
+		}
+	}
+}